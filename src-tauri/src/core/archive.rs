@@ -0,0 +1,77 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+
+use crate::core::engine::part_path;
+use crate::core::error::DownloadError;
+use crate::core::manifest::{normalize_path, safe_join, ManifestFile};
+use crate::log_err;
+
+/// Совпадает ли распакованный файл с записью манифеста по размеру и хэшу.
+fn matches_manifest(path: &Path, file: &ManifestFile) -> std::io::Result<bool> {
+    if let Some(size) = file.size {
+        if std::fs::metadata(path)?.len() != size {
+            return Ok(false);
+        }
+    }
+    match file.checksum() {
+        Some(checksum) => checksum.matches(path),
+        None => Ok(true),
+    }
+}
+
+/// Распаковывает из архива сборки только файлы из `wanted` (путь вида `dir/file` ->
+/// запись манифеста). Каждый файл пишется в `.part`, проверяется по манифесту и
+/// только тогда переименовывается на место, так что битый архив не портит файлы.
+/// Возвращает пути, которые удалось распаковать и проверить.
+pub fn extract_pack(
+    archive: &Path,
+    base: &Path,
+    wanted: &HashMap<String, ManifestFile>,
+    cancel: &CancellationToken,
+) -> Result<HashSet<String>, DownloadError> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)
+        .map_err(|e| DownloadError::SystemError(format!("Повреждённый архив сборки: {}", e)))?;
+
+    let mut extracted = HashSet::new();
+    for i in 0..zip.len() {
+        if cancel.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+
+        let mut entry = zip
+            .by_index(i)
+            .map_err(|e| DownloadError::SystemError(format!("Повреждённый архив сборки: {}", e)))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.enclosed_name().map(|p| normalize_path(&p.to_string_lossy())) else {
+            continue;
+        };
+        let Some(file) = wanted.get(&name) else {
+            continue;
+        };
+
+        let dest = safe_join(base, &name)?;
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let part = part_path(&dest);
+        let mut out = File::create(&part)?;
+        std::io::copy(&mut entry, &mut out)?;
+        out.sync_all()?;
+        drop(out);
+
+        if !matches_manifest(&part, file)? {
+            log_err!("Файл {} из архива сборки не совпал с манифестом, скачаем отдельно", name);
+            std::fs::remove_file(&part)?;
+            continue;
+        }
+        std::fs::rename(&part, &dest)?;
+
+        extracted.insert(name);
+    }
+
+    Ok(extracted)
+}
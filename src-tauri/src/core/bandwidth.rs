@@ -0,0 +1,225 @@
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::core::config::launcher_config;
+use crate::log_info;
+
+static BANDWIDTH: OnceLock<Bandwidth> = OnceLock::new();
+
+struct Bucket {
+    /// Байт в секунду; 0 — без ограничения.
+    limit: u64,
+    /// Доступные байты. Уходит в минус, если чанк больше остатка: следующий
+    /// чанк ждёт, пока долг не погасится.
+    tokens: f64,
+    refilled_at: Instant,
+    paused: bool,
+}
+
+impl Bucket {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.refilled_at = now;
+        // Запас не больше секунды трафика, чтобы после простоя не было всплеска
+        self.tokens = (self.tokens + elapsed * self.limit as f64).min(self.limit as f64);
+    }
+}
+
+/// Общий token bucket для всех загрузок движка. Лимит и пауза меняются на лету:
+/// ожидающие загрузки сразу пересчитывают время ожидания.
+pub struct Bandwidth {
+    bucket: Mutex<Bucket>,
+    changed: Notify,
+}
+
+pub fn bandwidth() -> &'static Bandwidth {
+    BANDWIDTH.get_or_init(|| {
+        let limit = launcher_config().bandwidth_limit;
+        Bandwidth {
+            bucket: Mutex::new(Bucket {
+                limit,
+                tokens: limit as f64,
+                refilled_at: Instant::now(),
+                paused: false,
+            }),
+            changed: Notify::new(),
+        }
+    })
+}
+
+impl Bandwidth {
+    fn bucket(&self) -> MutexGuard<'_, Bucket> {
+        match self.bucket.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn set_limit(&self, bytes_per_sec: u64) {
+        {
+            let mut bucket = self.bucket();
+            if bucket.limit == bytes_per_sec {
+                return;
+            }
+            bucket.refill();
+            bucket.limit = bytes_per_sec;
+            bucket.tokens = bucket.tokens.min(bytes_per_sec as f64);
+        }
+        log_info!("Ограничение скорости загрузки: {} Б/с", bytes_per_sec);
+        self.changed.notify_waiters();
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.bucket().paused = paused;
+        self.changed.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.bucket().paused
+    }
+
+    /// Учитывает `bytes` полученных байт и ждёт, пока лимит позволит читать
+    /// дальше. На паузе ждёт возобновления.
+    pub async fn consume(&self, bytes: usize) {
+        let mut charged = false;
+        loop {
+            // Подписываемся до проверки, чтобы не пропустить смену лимита или снятие паузы
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let wait = {
+                let mut bucket = self.bucket();
+                if bucket.paused {
+                    None
+                } else if bucket.limit == 0 {
+                    return;
+                } else {
+                    bucket.refill();
+                    if !charged {
+                        bucket.tokens -= bytes as f64;
+                        charged = true;
+                    }
+                    if bucket.tokens >= 0.0 {
+                        return;
+                    }
+                    Some(Duration::from_secs_f64(-bucket.tokens / bucket.limit as f64))
+                }
+            };
+
+            match wait {
+                Some(wait) => {
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {}
+                        _ = changed => {}
+                    }
+                }
+                None => changed.await,
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn pause_downloads() {
+    log_info!("Загрузки приостановлены");
+    bandwidth().set_paused(true);
+}
+
+#[tauri::command]
+pub fn resume_downloads() {
+    log_info!("Загрузки возобновлены");
+    bandwidth().set_paused(false);
+}
+
+#[tauri::command]
+pub fn downloads_paused() -> bool {
+    bandwidth().is_paused()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn limited(bytes_per_sec: u64) -> Arc<Bandwidth> {
+        Arc::new(Bandwidth {
+            bucket: Mutex::new(Bucket {
+                limit: bytes_per_sec,
+                tokens: bytes_per_sec as f64,
+                refilled_at: Instant::now(),
+                paused: false,
+            }),
+            changed: Notify::new(),
+        })
+    }
+
+    async fn timed(bandwidth: &Bandwidth, bytes: usize) -> Duration {
+        let start = Instant::now();
+        bandwidth.consume(bytes).await;
+        start.elapsed()
+    }
+
+    fn assert_about(actual: Duration, expected: Duration) {
+        let diff = actual.abs_diff(expected);
+        assert!(diff <= Duration::from_millis(5), "{:?} != {:?}", actual, expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_refill_at_limit_rate() {
+        let bandwidth = limited(1000);
+        assert_about(timed(&bandwidth, 1000).await, Duration::ZERO);
+        assert_about(timed(&bandwidth, 500).await, Duration::from_millis(500));
+        assert_about(timed(&bandwidth, 2000).await, Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_time_is_capped_at_one_second() {
+        let bandwidth = limited(1000);
+        assert_about(timed(&bandwidth, 1000).await, Duration::ZERO);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_about(timed(&bandwidth, 1000).await, Duration::ZERO);
+        assert_about(timed(&bandwidth, 1000).await, Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_never_waits() {
+        let bandwidth = limited(0);
+        assert_about(timed(&bandwidth, 10_000_000).await, Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn raising_limit_wakes_waiting_download() {
+        let bandwidth = limited(1000);
+        bandwidth.consume(1000).await;
+
+        let waiting = tokio::spawn({
+            let bandwidth = bandwidth.clone();
+            async move { timed(&bandwidth, 10_000).await }
+        });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        bandwidth.set_limit(0);
+        assert_about(waiting.await.unwrap(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_blocks_until_resumed() {
+        let bandwidth = limited(1000);
+        bandwidth.set_paused(true);
+
+        let waiting = tokio::spawn({
+            let bandwidth = bandwidth.clone();
+            async move { timed(&bandwidth, 100).await }
+        });
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert!(!waiting.is_finished());
+
+        bandwidth.set_paused(false);
+        assert_about(waiting.await.unwrap(), Duration::from_secs(30));
+        assert!(!bandwidth.is_paused());
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+use crate::core::downloader::get_base_dir;
+use crate::core::engine::engine;
+use crate::core::error::DownloadError;
+use crate::core::fs::write_atomic;
+use crate::{log_err, log_info};
+
+const CACHE_DIR: &str = ".limacina/cache";
+/// Сколько копия считается свежей и отдаётся без обращения к серверу.
+const METADATA_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Валидаторы и время последней проверки закэшированного ответа.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Секунды с эпохи Unix.
+    checked_at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn cache_paths(url: &str) -> Result<(PathBuf, PathBuf), DownloadError> {
+    let dir = PathBuf::from(get_base_dir()?).join(CACHE_DIR);
+    let key: String = Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok((dir.join(format!("{}.body", key)), dir.join(format!("{}.json", key))))
+}
+
+async fn load(url: &str, body_path: &Path, entry_path: &Path) -> Option<(CacheEntry, Vec<u8>)> {
+    let entry: CacheEntry = serde_json::from_slice(&fs::read(entry_path).await.ok()?).ok()?;
+    if entry.url != url {
+        return None;
+    }
+    let body = fs::read(body_path).await.ok()?;
+    Some((entry, body))
+}
+
+async fn store(body_path: &Path, entry_path: &Path, entry: &CacheEntry, body: Option<Vec<u8>>) {
+    let (body_path, entry_path) = (body_path.to_path_buf(), entry_path.to_path_buf());
+    let entry_json = serde_json::to_vec(entry);
+    let result = tokio::task::spawn_blocking(move || -> Result<(), DownloadError> {
+        if let Some(body) = body {
+            write_atomic(&body_path, &body)?;
+        }
+        Ok(write_atomic(&entry_path, &entry_json?)?)
+    })
+    .await
+    .map_err(DownloadError::from)
+    .and_then(|result| result);
+
+    if let Err(e) = result {
+        log_err!("Не удалось сохранить кэш {}: {}", entry.url, e);
+    }
+}
+
+/// Метаданные (списки версий и т.п.) через кэш в папке лаунчера. Свежая копия
+/// отдаётся сразу, устаревшая перепроверяется условным запросом, а без сети
+/// используется то, что есть в кэше.
+pub async fn fetch_cached(url: &str) -> Result<Vec<u8>, DownloadError> {
+    let (body_path, entry_path) = cache_paths(url)?;
+    let cached = load(url, &body_path, &entry_path).await;
+
+    if let Some((entry, body)) = &cached {
+        if now_secs().saturating_sub(entry.checked_at) < METADATA_TTL.as_secs() {
+            return Ok(body.clone());
+        }
+    }
+
+    let (etag, last_modified) = match &cached {
+        Some((entry, _)) => (entry.etag.as_deref(), entry.last_modified.as_deref()),
+        None => (None, None),
+    };
+
+    match (engine().fetch_conditional(url, etag, last_modified).await, cached) {
+        (Ok(Some(response)), _) => {
+            let entry = CacheEntry {
+                url: url.to_string(),
+                etag: response.etag,
+                last_modified: response.last_modified,
+                checked_at: now_secs(),
+            };
+            store(&body_path, &entry_path, &entry, Some(response.bytes.clone())).await;
+            Ok(response.bytes)
+        }
+        (Ok(None), Some((mut entry, body))) => {
+            entry.checked_at = now_secs();
+            store(&body_path, &entry_path, &entry, None).await;
+            Ok(body)
+        }
+        (Ok(None), None) => Err(DownloadError::SystemError(format!(
+            "Сервер ответил 304 на запрос без кэша: {}",
+            url
+        ))),
+        (Err(DownloadError::Cancelled), _) => Err(DownloadError::Cancelled),
+        (Err(DownloadError::Offline), Some((_, body))) => Ok(body),
+        (Err(e), Some((_, body))) => {
+            log_err!("Не удалось обновить {}: {}", url, e);
+            log_info!("Используем копию из кэша: {}", url);
+            Ok(body)
+        }
+        (Err(e), None) => Err(e),
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tokio_util::sync::CancellationToken;
+
+use crate::log_info;
+
+pub const INSTALL_OPERATION: &str = "minecraft-install";
+pub const SYNC_OPERATION: &str = "modpack-sync";
+
+static OPERATIONS: OnceLock<Mutex<HashMap<String, (u64, CancellationToken)>>> = OnceLock::new();
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn operations() -> std::sync::MutexGuard<'static, HashMap<String, (u64, CancellationToken)>> {
+    let map = OPERATIONS.get_or_init(|| Mutex::new(HashMap::new()));
+    match map.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Регистрация выполняющейся операции. Пока guard жив, операцию можно отменить
+/// через `cancel_operation`; при удалении guard'а запись снимается.
+pub struct OperationGuard {
+    id: String,
+    generation: u64,
+    token: CancellationToken,
+}
+
+impl OperationGuard {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let mut operations = operations();
+        // Запись могла быть заменена более новым запуском с тем же id
+        if matches!(operations.get(&self.id), Some((generation, _)) if *generation == self.generation) {
+            operations.remove(&self.id);
+        }
+    }
+}
+
+/// Начинает операцию с данным id. Предыдущая операция с тем же id отменяется.
+pub fn begin_operation(id: &str) -> OperationGuard {
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    let token = CancellationToken::new();
+
+    if let Some((_, previous)) = operations().insert(id.to_string(), (generation, token.clone())) {
+        previous.cancel();
+    }
+
+    OperationGuard {
+        id: id.to_string(),
+        generation,
+        token,
+    }
+}
+
+pub fn is_running(id: &str) -> bool {
+    operations().contains_key(id)
+}
+
+#[tauri::command]
+pub fn cancel_operation(operation_id: String) -> bool {
+    match operations().get(&operation_id) {
+        Some((_, token)) => {
+            log_info!("Отмена операции: {}", operation_id);
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,316 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+
+use crate::core::bandwidth::bandwidth;
+use crate::core::downloader::get_base_dir;
+use crate::core::engine::{build_client, engine};
+use crate::core::error::DownloadError;
+use crate::core::fs::write_atomic;
+use crate::core::proxy::reqwest_proxy;
+use crate::{log_err, log_info};
+
+const CONFIG_FILE: &str = "launcher_config.json";
+const DEFAULT_SYNC_LIST_URL: &str = "https://strapi.tekina.ru/api/list";
+const DEFAULT_SYNC_PROXY_URL: &str = "https://strapi.tekina.ru/api/test";
+
+static CONFIG: OnceLock<RwLock<LauncherConfig>> = OnceLock::new();
+
+/// Как скачивать файлы сборки, для которых в манифесте нет прямой ссылки.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncTransport {
+    /// POST `{ "url": <путь файла> }` на прокси, который отдаёт содержимое файла.
+    Proxy { url: String },
+    /// GET `<base_url>/<путь файла>`.
+    Direct { base_url: String },
+}
+
+/// Что делать с файлами в управляемых папках, которых нет в манифесте.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleFilesMode {
+    /// Перенести в `.limacina/quarantine`, чтобы файл можно было вернуть.
+    #[default]
+    Quarantine,
+    Delete,
+    /// Ничего не удалять.
+    Keep,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Адрес манифеста сборки.
+    pub list_url: String,
+    /// Адрес detached-подписи манифеста; по умолчанию `<list_url>.sig`.
+    pub signature_url: Option<String>,
+    /// Манифесты-оверлеи, применяемые по порядку поверх `list_url`.
+    /// Подпись каждого лежит рядом, в `<url>.sig`.
+    pub overlays: Vec<String>,
+    pub transport: SyncTransport,
+    /// Bearer-токен для запросов к серверу синхронизации.
+    pub token: Option<String>,
+    pub stale_files: StaleFilesMode,
+    /// Доля недостающих файлов, начиная с которой вместо пофайловой загрузки
+    /// скачивается архив всей сборки (если манифест его предлагает).
+    pub archive_threshold: f64,
+    /// Сверять `mods/` и `config/` с манифестом перед запуском игры.
+    pub verify_before_launch: bool,
+    /// Период фоновой проверки обновлений сборки в секундах; 0 — выключено.
+    pub update_check_interval: u64,
+    /// Glob-шаблоны файлов пользователя, которые синхронизация не трогает.
+    pub keep: Vec<String>,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            list_url: DEFAULT_SYNC_LIST_URL.to_string(),
+            signature_url: None,
+            overlays: Vec::new(),
+            transport: SyncTransport::Proxy {
+                url: DEFAULT_SYNC_PROXY_URL.to_string(),
+            },
+            token: None,
+            stale_files: StaleFilesMode::default(),
+            archive_threshold: 0.5,
+            verify_before_launch: false,
+            update_check_interval: 30 * 60,
+            keep: vec![
+                "resourcepacks/**".to_string(),
+                "shaderpacks/**".to_string(),
+                "config/**/*.local.*".to_string(),
+            ],
+        }
+    }
+}
+
+impl SyncConfig {
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref().filter(|t| !t.is_empty())
+    }
+
+    pub fn signature_url(&self) -> String {
+        self.signature_url
+            .clone()
+            .unwrap_or_else(|| format!("{}.sig", self.list_url))
+    }
+
+    fn validate(&self) -> Result<(), DownloadError> {
+        validate_url(&self.list_url)?;
+        if let Some(url) = &self.signature_url {
+            validate_url(url)?;
+        }
+        for url in &self.overlays {
+            validate_url(url)?;
+        }
+        match &self.transport {
+            SyncTransport::Proxy { url } => validate_url(url),
+            SyncTransport::Direct { base_url } => validate_url(base_url),
+        }
+    }
+}
+
+/// Готовые наборы зеркал для серверов Mojang, Fabric и Forge.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorPreset {
+    /// Только официальные серверы.
+    #[default]
+    Official,
+    /// BMCLAPI (bmclapi2.bangbang93.com).
+    Bmclapi,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct MirrorConfig {
+    pub preset: MirrorPreset,
+    /// Свои зеркала: хост -> базовые адреса, которые пробуются по порядку
+    /// раньше зеркал из `preset`. Официальный сервер остаётся последним.
+    pub hosts: BTreeMap<String, Vec<String>>,
+    /// Один сервер вместо всех: запрос к `https://<host>/<путь>` уходит на
+    /// `<override_url>/<host>/<путь>`, без запасных вариантов. Для тестов.
+    pub override_url: Option<String>,
+}
+
+impl MirrorConfig {
+    fn validate(&self) -> Result<(), DownloadError> {
+        if let Some(url) = &self.override_url {
+            validate_url(url)?;
+        }
+        for url in self.hosts.values().flatten() {
+            validate_url(url)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyKind {
+    #[default]
+    Http,
+    Https,
+    Socks5,
+}
+
+/// Прокси для всех запросов лаунчера (и, по желанию, для игры).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub enabled: bool,
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Хосты, к которым ходим напрямую: `example.com`, `.example.com`, `10.0.0.0/8`.
+    pub no_proxy: Vec<String>,
+    /// Передавать прокси игре через `-Dhttp.proxyHost` и подобные свойства.
+    pub apply_to_game: bool,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kind: ProxyKind::default(),
+            host: String::new(),
+            port: 8080,
+            username: None,
+            password: None,
+            no_proxy: Vec::new(),
+            apply_to_game: false,
+        }
+    }
+}
+
+impl ProxyConfig {
+    fn validate(&self) -> Result<(), DownloadError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.host.trim().is_empty() || self.port == 0 {
+            return Err(DownloadError::SystemError(
+                "Для прокси нужно указать адрес и порт".to_string(),
+            ));
+        }
+        reqwest_proxy(self).map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LauncherConfig {
+    pub sync: SyncConfig,
+    pub mirrors: MirrorConfig,
+    pub proxy: ProxyConfig,
+    /// Автономный режим: лаунчер не обращается к сети, игра запускается
+    /// из локально установленных версий, синхронизация сборки пропускается.
+    pub offline: bool,
+    /// Общий лимит скорости загрузок в байтах в секунду; 0 — без ограничения.
+    pub bandwidth_limit: u64,
+}
+
+impl LauncherConfig {
+    fn validate(&self) -> Result<(), DownloadError> {
+        self.sync.validate()?;
+        self.mirrors.validate()?;
+        self.proxy.validate()
+    }
+}
+
+/// Разрешаем только HTTPS; HTTP — лишь для локального тестового сервера.
+fn validate_url(url: &str) -> Result<(), DownloadError> {
+    let parsed = Url::parse(url)
+        .map_err(|e| DownloadError::SystemError(format!("Некорректный адрес {}: {}", url, e)))?;
+
+    let is_local = matches!(parsed.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if is_local => Ok(()),
+        _ => Err(DownloadError::SystemError(format!(
+            "Адрес сервера должен использовать HTTPS: {}",
+            url
+        ))),
+    }
+}
+
+fn config_path() -> Result<PathBuf, DownloadError> {
+    Ok(PathBuf::from(get_base_dir()?).join(CONFIG_FILE))
+}
+
+fn load_from_disk() -> LauncherConfig {
+    let path = match config_path() {
+        Ok(path) => path,
+        Err(_) => return LauncherConfig::default(),
+    };
+    let Ok(data) = std::fs::read_to_string(&path) else {
+        return LauncherConfig::default();
+    };
+
+    match serde_json::from_str::<LauncherConfig>(&data) {
+        Ok(mut config) => {
+            if let Err(e) = config.sync.validate() {
+                log_err!("Настройки синхронизации в {} некорректны, используются значения по умолчанию: {}", path.display(), e);
+                config.sync = SyncConfig::default();
+            }
+            if let Err(e) = config.mirrors.validate() {
+                log_err!("Настройки зеркал в {} некорректны, используются значения по умолчанию: {}", path.display(), e);
+                config.mirrors = MirrorConfig::default();
+            }
+            // Некорректный прокси не сбрасываем: иначе трафик молча пошёл бы напрямую.
+            // Движок с таким прокси отказывается выполнять запросы.
+            config
+        }
+        Err(e) => {
+            log_err!("Не удалось прочитать {}: {}", path.display(), e);
+            LauncherConfig::default()
+        }
+    }
+}
+
+fn config_lock() -> &'static RwLock<LauncherConfig> {
+    CONFIG.get_or_init(|| RwLock::new(load_from_disk()))
+}
+
+/// Текущие настройки лаунчера (читаются с диска при первом обращении).
+pub fn launcher_config() -> LauncherConfig {
+    match config_lock().read() {
+        Ok(config) => config.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+fn save_config(config: LauncherConfig) -> Result<(), DownloadError> {
+    config.validate()?;
+    // Клиент собираем до сохранения, чтобы не записать настройки, с которыми он не строится
+    let client = build_client(&config.proxy)?;
+
+    let path = config_path()?;
+    write_atomic(&path, &serde_json::to_vec_pretty(&config)?)?;
+
+    let bandwidth_limit = config.bandwidth_limit;
+    match config_lock().write() {
+        Ok(mut current) => *current = config,
+        Err(poisoned) => *poisoned.into_inner() = config,
+    }
+    engine().replace_client(client);
+    bandwidth().set_limit(bandwidth_limit);
+    log_info!("Настройки лаунчера сохранены: {}", path.display());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_launcher_config() -> LauncherConfig {
+    launcher_config()
+}
+
+#[tauri::command]
+pub fn set_launcher_config(config: LauncherConfig) -> Result<(), DownloadError> {
+    save_config(config)
+}
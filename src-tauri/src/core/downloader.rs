@@ -1,109 +1,71 @@
-use futures_util::StreamExt;
-use md5::{Digest, Md5};
-use reqwest::Client;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs::{self, File};
-use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
-use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
 
-const MAX_CONCURRENT_DOWNLOADS: usize = 20;
+use crate::core::archive::extract_pack;
 
-#[derive(Debug, thiserror::Error)]
-pub enum DownloadError {
-    #[error("Не удалось получить файл от сервера: {0}")]
-    FetchError(#[from] reqwest::Error),
+use crate::core::cancel::{begin_operation, SYNC_OPERATION};
+use crate::core::config::{launcher_config, StaleFilesMode, SyncConfig, SyncTransport};
+use crate::core::engine::{engine, Checksum, DownloadRequest};
+use crate::core::error::DownloadError;
+use crate::core::fingerprint::FingerprintIndex;
+use crate::core::groups::{disabled_files, load_choices};
+use crate::core::manifest::{FilePolicy, ManifestFile, ModpackManifest, PackArchive};
+use crate::core::patch::update_with_patch;
+use crate::core::progress::{InstallStage, ProgressTracker};
+use crate::core::signature;
+use crate::core::stale::{build_allow_list, find_stale_files, remove_stale_files, RemovedFile, StaleScope};
+use crate::{log_err, log_info};
 
-    #[error("Не удалось получить размер файла из заголовков")]
-    ContentLengthError,
-
-    #[error("Ошибка файловой системы: {0}")]
-    IoError(#[from] std::io::Error),
-
-    #[error("Ошибка JSON: {0}")]
-    JsonError(#[from] serde_json::Error),
-
-    #[error("Системная ошибка: {0}")]
-    SystemError(String),
-
-    #[error("Ошибка выполнения задачи: {0}")]
-    JoinError(#[from] tokio::task::JoinError),
-}
-
-impl Serialize for DownloadError {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&self.to_string())
-    }
-}
-
-#[derive(Clone, Serialize)]
-struct TotalProgressPayload {
-    completed: usize,
-    total: usize,
-    percent: f64,
-    current_file: String,
-}
+const PACK_ARCHIVE_FILE: &str = ".limacina/pack.zip";
 
 #[derive(Serialize)]
 struct BodyFile {
     url: String,
 }
 
-fn get_file_hash(file_path: &PathBuf) -> Result<String, DownloadError> {
-    let mut file = File::open(file_path)?;
-    let mut hasher = Md5::new();
-    let mut buffer = [0u8; 8192];
+pub(crate) fn download_request(sync: &SyncConfig, file_path: PathBuf, file: &ManifestFile) -> DownloadRequest {
+    // Сжатая копия лежит рядом с файлом: `mods/a.jar.zst`
+    let payload_path = match file.compression {
+        Some(compression) => format!("{}{}", file.path, compression.extension()),
+        None => file.path.clone(),
+    };
 
-    loop {
-        let bytes_read = file.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+    // Прямая ссылка из манифеста может вести на сторонний CDN — токен туда не отправляем
+    let request = match (&file.url, &sync.transport) {
+        (Some(url), _) => DownloadRequest::new(url, file_path),
+        (None, SyncTransport::Direct { base_url }) => {
+            let url = format!("{}/{}", base_url.trim_end_matches('/'), payload_path.trim_start_matches('/'));
+            DownloadRequest::new(url, file_path).bearer_auth(sync.token())
+        }
+        (None, SyncTransport::Proxy { url }) => {
+            let body = BodyFile {
+                url: payload_path,
+            };
+            DownloadRequest::new(url, file_path)
+                .body(serde_json::json!(body))
+                .bearer_auth(sync.token())
         }
-        hasher.update(&buffer[..bytes_read]);
-    }
-
-    Ok(format!("{:x}", hasher.finalize()))
-}
-
-async fn download_file(
-    client: &Client,
-    file_path: &PathBuf,
-    url: &str,
-) -> Result<(), DownloadError> {
-    let body = BodyFile {
-        url: url.to_string(),
     };
 
-    let response = client
-        .post("http://strapi.tekina.ru/api/test")
-        .json(&body)
-        .send()
-        .await?;
-
-    if let Some(parent) = Path::new(file_path).parent() {
-        fs::create_dir_all(parent)?;
-    }
-
-    let mut file = File::create(file_path)?;
-    let mut stream = response.bytes_stream();
-
-    while let Some(item) = stream.next().await {
-        let chunk = item?;
-        file.write_all(&chunk)?;
+    let request = match file.compression {
+        Some(compression) => request.compression(compression),
+        None => request,
+    };
+    let request = match file.checksum() {
+        Some(checksum) => request.checksum(checksum),
+        None => request,
+    };
+    match file.size {
+        Some(size) => request.size(size),
+        None => request,
     }
-
-    Ok(())
 }
 
-fn get_base_dir() -> Result<String, DownloadError> {
+pub(crate) fn get_base_dir() -> Result<String, DownloadError> {
     #[allow(deprecated)]
     let home_dir: PathBuf = env::home_dir()
         .ok_or_else(|| DownloadError::SystemError("Home directory not found".to_string()))?;
@@ -116,94 +78,314 @@ fn get_base_dir() -> Result<String, DownloadError> {
     Ok(dir.to_string_lossy().to_string())
 }
 
-#[tauri::command]
-pub async fn download_all_files(app: AppHandle) -> Result<String, DownloadError> {
-    let client = Client::new();
+/// Манифест не применяется, пока его подпись не проверена зашитым ключом.
+async fn verify_signature(sync: &SyncConfig, manifest: &[u8], signature_url: &str) -> Result<(), DownloadError> {
+    if !signature::is_enforced() {
+        return signature::verify_manifest(manifest, None);
+    }
 
-    let response = client
-        .get("http://strapi.tekina.ru/api/list")
-        .send()
-        .await?;
+    let signature = match engine()
+        .fetch_bytes_with_token(signature_url, sync.token())
+        .await
+    {
+        Ok(signature) => Some(signature),
+        Err(e) => {
+            log_err!("Не удалось получить подпись манифеста: {}", e);
+            None
+        }
+    };
+    signature::verify_manifest(manifest, signature.as_deref())
+}
 
-    let file_list: HashMap<String, String> = response.json().await?;
+async fn fetch_layer(sync: &SyncConfig, url: &str, signature_url: &str) -> Result<ModpackManifest, DownloadError> {
+    let manifest_bytes = engine().fetch_bytes_with_token(url, sync.token()).await?;
+    verify_signature(sync, &manifest_bytes, signature_url).await?;
+    ModpackManifest::parse(&manifest_bytes)
+}
 
-    let base_dir_str = get_base_dir()?;
-    let core = PathBuf::from(&base_dir_str);
+/// Скачивает базовый манифест и оверлеи из настроек, проверяет подпись каждого
+/// и сливает их в один.
+pub(crate) async fn fetch_manifest() -> Result<ModpackManifest, DownloadError> {
+    let sync = launcher_config().sync;
 
-    let mut files_to_download: Vec<String> = Vec::new();
+    let base = fetch_layer(&sync, &sync.list_url, &sync.signature_url()).await?;
+    let mut layers = vec![(base.name.clone().unwrap_or_else(|| "base".to_string()), base)];
 
-    for (key, expected_hash) in &file_list {
-        let file_path = core.join(key);
-        if !file_path.exists() {
-            files_to_download.push(key.clone());
-        } else {
-            match get_file_hash(&file_path) {
-                Ok(hash) if hash == *expected_hash => continue,
-                _ => files_to_download.push(key.clone()),
-            }
-        }
+    for url in &sync.overlays {
+        let overlay = fetch_layer(&sync, url, &format!("{}.sig", url)).await?;
+        let name = overlay.name.clone().unwrap_or_else(|| url.clone());
+        log_info!("Оверлей сборки: {}", name);
+        layers.push((name, overlay));
     }
 
-    let total_files = files_to_download.len();
-    let _ = app.emit("totalFile", total_files);
+    Ok(ModpackManifest::merge(layers))
+}
 
-    if total_files == 0 {
-        return Ok(serde_json::to_string(&file_list)?);
+/// Скачивает архив всей сборки и распаковывает из него недостающие файлы.
+/// Возвращает пути распакованных файлов, уже проверенных по манифесту.
+async fn install_from_archive(
+    app: &AppHandle,
+    core: &Path,
+    archive: &PackArchive,
+    wanted: HashMap<String, ManifestFile>,
+    cancel: &CancellationToken,
+) -> Result<HashSet<String>, DownloadError> {
+    let archive_path = core.join(PACK_ARCHIVE_FILE);
+    let mut request = DownloadRequest::new(&archive.url, archive_path.clone()).cancel(cancel);
+    if let Some(sha256) = &archive.sha256 {
+        request = request.checksum(Checksum::Sha256(sha256.clone()));
+    }
+    if let Some(size) = archive.size {
+        request = request.size(size);
     }
 
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS));
-    let completed_counter = Arc::new(AtomicUsize::new(0));
-    let mut tasks = Vec::new();
+    log_info!("Скачиваем архив сборки: {}", archive.url);
+    let progress = ProgressTracker::new(app, InstallStage::ModpackSync, 1, archive.size.unwrap_or(0));
+    let result = engine().download(&request.progress(&progress)).await;
+    progress.finish();
+    result?;
+
+    let base = core.to_path_buf();
+    let cancel = cancel.clone();
+    let extracted = tokio::task::spawn_blocking(move || {
+        extract_pack(&archive_path, &base, &wanted, &cancel)
+    })
+    .await?;
+
+    let _ = tokio::fs::remove_file(core.join(PACK_ARCHIVE_FILE)).await;
+    extracted
+}
 
-    for file_key in files_to_download {
-        let sem = semaphore.clone();
-        let client = client.clone();
-        let app = app.clone();
-        let counter = completed_counter.clone();
+/// Что синхронизация делает с записью манифеста.
+pub(crate) enum FileAction {
+    /// Привести файл к версии из манифеста.
+    Sync,
+    /// Удалить файл у игрока.
+    Remove,
+    /// Ничего не делать.
+    Leave,
+}
 
-        let core_path = core.clone();
-        let file_key_clone = file_key.clone();
+pub(crate) fn file_action(file: &ManifestFile, file_path: &Path, disabled: &HashSet<String>) -> FileAction {
+    let exists = file_path.exists();
+    // Файлы выключенных групп удаляются так же, как помеченные `delete`
+    if file.policy == FilePolicy::Delete || disabled.contains(&file.normalized_path()) {
+        return if exists { FileAction::Remove } else { FileAction::Leave };
+    }
+    // Существующий файл не трогаем, даже если пользователь его изменил
+    if file.policy == FilePolicy::CreateOnly && exists {
+        return FileAction::Leave;
+    }
+    FileAction::Sync
+}
 
-        let total_files_count = total_files;
+/// Итог синхронизации сборки для фронтенда.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub downloaded: Vec<SyncedFile>,
+    /// Необязательные файлы, которые не удалось скачать.
+    pub skipped: Vec<SyncedFile>,
+    pub removed: Vec<RemovedFile>,
+    /// Синхронизация не выполнялась: включён автономный режим.
+    pub offline: bool,
+}
 
-        let handle = tokio::spawn(async move {
-            let _permit = sem.acquire().await.map_err(|e| DownloadError::SystemError(e.to_string()))?;
+#[derive(Debug, Serialize)]
+pub struct SyncedFile {
+    pub path: String,
+    /// Слой манифеста, из которого взят файл.
+    pub layer: String,
+}
 
-            let file_name = Path::new(&file_key_clone)
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_else(|| file_key_clone.clone());
+impl From<&ManifestFile> for SyncedFile {
+    fn from(file: &ManifestFile) -> Self {
+        Self {
+            path: file.normalized_path(),
+            layer: file.layer.clone(),
+        }
+    }
+}
 
-            let file_path = core_path.join(&file_key_clone);
+#[tauri::command]
+pub async fn download_all_files(
+    app: AppHandle,
+    operation_id: Option<String>,
+    force_verify: Option<bool>,
+) -> Result<SyncReport, DownloadError> {
+    let stale_files = launcher_config().sync.stale_files;
+    sync_modpack(
+        &app,
+        operation_id,
+        force_verify.unwrap_or(false),
+        stale_files,
+        StaleScope::Installed,
+    )
+    .await
+}
 
-            download_file(&client, &file_path, &file_key_clone).await?;
+/// Синхронизация сборки; `stale_files` и `stale_scope` задают, что делать с
+/// лишними файлами, независимо от настроек (нужно для восстановления перед запуском).
+pub(crate) async fn sync_modpack(
+    app: &AppHandle,
+    operation_id: Option<String>,
+    force_verify: bool,
+    stale_files: StaleFilesMode,
+    stale_scope: StaleScope,
+) -> Result<SyncReport, DownloadError> {
+    if launcher_config().offline {
+        log_err!("Автономный режим: синхронизация сборки пропущена, файлы могут быть устаревшими");
+        return Ok(SyncReport {
+            offline: true,
+            ..SyncReport::default()
+        });
+    }
 
-            let completed = counter.fetch_add(1, Ordering::SeqCst) + 1;
+    let operation = begin_operation(operation_id.as_deref().unwrap_or(SYNC_OPERATION));
 
-            /*let _ = app.emit(
-                "numberFile",
-                TotalProgressPayload {
-                    completed,
-                    total: total_files_count,
-                    percent: (completed as f64 / total_files_count as f64 * 100.0).round(),
-                    current_file: file_name,
-                },
-            );*/
+    let sync = launcher_config().sync;
+    let manifest = fetch_manifest().await?;
 
-            Ok::<(), DownloadError>(())
-        });
+    let base_dir_str = get_base_dir()?;
+    let core = PathBuf::from(&base_dir_str);
 
-        tasks.push(handle);
+    let mut report = SyncReport::default();
+    let mut files_to_delete: Vec<String> = Vec::new();
+    let mut candidates: Vec<(usize, String, DownloadRequest)> = Vec::new();
+    let disabled = disabled_files(&manifest, &load_choices(&core));
+
+    for (index, file) in manifest.files.iter().enumerate() {
+        let file_path = file.local_path(&core)?;
+        match file_action(file, &file_path, &disabled) {
+            FileAction::Remove => files_to_delete.push(file.normalized_path()),
+            FileAction::Leave => {}
+            FileAction::Sync => {
+                let request = download_request(&sync, file_path, file);
+                candidates.push((index, file.normalized_path(), request));
+            }
+        }
+    }
+    let eligible_files = candidates.len();
+
+    // Хэширование (с force_verify — всей сборки) блокирует поток, поэтому идёт в
+    // отдельном пуле и прерывается отменой между файлами
+    let mut fingerprints = FingerprintIndex::load(&core);
+    let cancel = operation.token().clone();
+    let (mut fingerprints, outdated) = tokio::task::spawn_blocking(move || {
+        let mut outdated = Vec::new();
+        for (index, relative, request) in candidates {
+            if cancel.is_cancelled() {
+                fingerprints.save();
+                return Err(DownloadError::Cancelled);
+            }
+            if !fingerprints.verify(&relative, &request, force_verify) {
+                outdated.push((index, request));
+            }
+        }
+        fingerprints.save();
+        Ok((fingerprints, outdated))
+    })
+    .await??;
+    let mut files_to_download: Vec<(DownloadRequest, &ManifestFile)> = outdated
+        .into_iter()
+        .map(|(index, request)| (request, &manifest.files[index]))
+        .collect();
+
+    // На первой установке один архив быстрее тысяч отдельных запросов; изменённые
+    // файлы в долю не входят, их обычно немного и они качаются или патчатся отдельно
+    let missing_files = files_to_download.iter().filter(|(request, _)| !request.dest.exists()).count();
+    let missing_share = missing_files as f64 / eligible_files.max(1) as f64;
+    if let Some(archive) = manifest.archive.as_ref().filter(|_| {
+        missing_files > 1 && missing_share >= sync.archive_threshold
+    }) {
+        let wanted = files_to_download
+            .iter()
+            .map(|(_, file)| (file.normalized_path(), (*file).clone()))
+            .collect();
+
+        match install_from_archive(app, &core, archive, wanted, operation.token()).await {
+            Ok(extracted) => {
+                files_to_download.retain(|(request, file)| {
+                    let path = file.normalized_path();
+                    let installed = extracted.contains(&path);
+                    if installed {
+                        fingerprints.record(&path, request);
+                        report.downloaded.push((*file).into());
+                    }
+                    !installed
+                });
+                fingerprints.save();
+            }
+            Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+            Err(e) => {
+                log_err!("Не удалось установить сборку из архива, скачиваем по файлам: {}", e);
+            }
+        }
     }
 
-    let results = futures::future::join_all(tasks).await;
+    // Изменённые файлы, для которых есть патч с локальной версии, обновляем на месте
+    let mut patched = HashSet::new();
+    for (request, file) in &files_to_download {
+        match update_with_patch(&core, file, request, operation.token()).await {
+            Ok(true) => {
+                fingerprints.record(&file.normalized_path(), request);
+                report.downloaded.push((*file).into());
+                patched.insert(file.normalized_path());
+            }
+            Ok(false) => {}
+            Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+            Err(e) => {
+                log_err!("Не удалось применить патч к {}, скачиваем целиком: {}", file.path, e);
+            }
+        }
+    }
+    files_to_download.retain(|(_, file)| !patched.contains(&file.normalized_path()));
 
-    for res in results {
-        match res {
-            Ok(inner_result) => inner_result?,
-            Err(e) => return Err(DownloadError::JoinError(e)),
+    let total_files = files_to_download.len();
+    let _ = app.emit("totalFile", total_files);
+
+    if total_files > 0 {
+        let total_size = files_to_download.iter().filter_map(|(r, _)| r.size).sum();
+        let progress = ProgressTracker::new(app, InstallStage::ModpackSync, total_files as u64, total_size);
+        let (requests, files): (Vec<_>, Vec<_>) = files_to_download
+            .into_iter()
+            .map(|(request, file)| (request.progress(&progress).cancel(operation.token()), file))
+            .unzip();
+
+        let results = engine().download_all(requests).await;
+        progress.finish();
+
+        for ((request, result), file) in results.into_iter().zip(files) {
+            match result {
+                Ok(_) => {
+                    fingerprints.record(&file.normalized_path(), &request);
+                    report.downloaded.push(file.into());
+                }
+                Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+                Err(e) if file.policy == FilePolicy::Optional => {
+                    log_err!("Необязательный файл {} пропущен: {}", file.path, e);
+                    report.skipped.push(file.into());
+                }
+                Err(e) => return Err(e),
+            }
         }
     }
 
-    Ok("Все скачено успешно".to_string())
-}
\ No newline at end of file
+    // Удаляем только после успешного скачивания, чтобы сбой не оставил игрока без модов
+    report.removed = remove_stale_files(&core, files_to_delete, StaleFilesMode::Delete).await;
+
+    let allow_list = build_allow_list(sync.keep.iter().chain(&manifest.keep))?;
+    let stale = find_stale_files(&core, &manifest, &allow_list, &fingerprints, stale_scope)?;
+    if !stale.is_empty() {
+        log_info!("Лишних файлов в сборке: {}", stale.len());
+    }
+    report
+        .removed
+        .extend(remove_stale_files(&core, stale, stale_files).await);
+
+    for removed in &report.removed {
+        fingerprints.remove(&removed.path);
+    }
+    fingerprints.save();
+
+    Ok(report)
+}
@@ -0,0 +1,784 @@
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use futures_util::{StreamExt, TryStreamExt};
+use md5::{Digest, Md5};
+use reqwest::header::{HeaderName, CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use sha1::Sha1;
+use sha2::Sha256;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio_util::io::StreamReader;
+use tokio_util::sync::CancellationToken;
+
+use crate::core::bandwidth::bandwidth;
+use crate::core::config::{launcher_config, ProxyConfig};
+use crate::core::error::DownloadError;
+use crate::core::mirror;
+use crate::core::proxy::reqwest_proxy;
+use crate::core::progress::ProgressTracker;
+use crate::{log_err, log_info};
+
+const MAX_CONCURRENT_DOWNLOADS: usize = 20;
+const MAX_CONCURRENT_METADATA: usize = 4;
+const MAX_VERIFY_ATTEMPTS: u32 = 2;
+const MAX_RETRIES: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; MinecraftLauncher/1.0)";
+const DECODE_BUFFER_SIZE: usize = 64 * 1024;
+
+static ENGINE: OnceLock<DownloadEngine> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checksum {
+    Sha1(String),
+    Sha256(String),
+    Md5(String),
+}
+
+impl Checksum {
+    pub fn matches(&self, path: &Path) -> std::io::Result<bool> {
+        Ok(match self {
+            Checksum::Sha1(expected) => file_digest::<Sha1>(path)?.eq_ignore_ascii_case(expected),
+            Checksum::Sha256(expected) => file_digest::<Sha256>(path)?.eq_ignore_ascii_case(expected),
+            Checksum::Md5(expected) => file_digest::<Md5>(path)?.eq_ignore_ascii_case(expected),
+        })
+    }
+}
+
+/// Хэш, который считается по ходу записи файла, чтобы проверка после
+/// загрузки не требовала ещё одного чтения с диска.
+enum StreamingHash {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Md5(Md5),
+}
+
+impl StreamingHash {
+    fn new(checksum: &Checksum) -> Self {
+        match checksum {
+            Checksum::Sha1(_) => StreamingHash::Sha1(Sha1::new()),
+            Checksum::Sha256(_) => StreamingHash::Sha256(Sha256::new()),
+            Checksum::Md5(_) => StreamingHash::Md5(Md5::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            StreamingHash::Sha1(hasher) => hasher.update(data),
+            StreamingHash::Sha256(hasher) => hasher.update(data),
+            StreamingHash::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Учитывает уже скачанную часть файла при докачке.
+    fn update_from_file(&mut self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::fs::File::open(path)?;
+        let mut buffer = [0u8; 8192];
+        loop {
+            let bytes_read = file.read(&mut buffer)?;
+            if bytes_read == 0 {
+                return Ok(());
+            }
+            self.update(&buffer[..bytes_read]);
+        }
+    }
+
+    fn matches(self, checksum: &Checksum) -> bool {
+        let actual = match self {
+            StreamingHash::Sha1(hasher) => hex_lower(&hasher.finalize()),
+            StreamingHash::Sha256(hasher) => hex_lower(&hasher.finalize()),
+            StreamingHash::Md5(hasher) => hex_lower(&hasher.finalize()),
+        };
+        let expected = match checksum {
+            Checksum::Sha1(hash) | Checksum::Sha256(hash) | Checksum::Md5(hash) => hash,
+        };
+        actual.eq_ignore_ascii_case(expected)
+    }
+}
+
+/// Сжатие, в котором сервер отдаёт файл. Распаковка идёт на лету,
+/// а хэш и размер проверяются уже у распакованного файла.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Zstd,
+    Gzip,
+}
+
+impl Compression {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Zstd => ".zst",
+            Compression::Gzip => ".gz",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadRequest {
+    pub url: String,
+    pub dest: PathBuf,
+    pub checksum: Option<Checksum>,
+    pub size: Option<u64>,
+    /// Если задано — файл запрашивается POST-запросом с этим JSON вместо GET.
+    pub body: Option<serde_json::Value>,
+    /// Bearer-токен для заголовка `Authorization`.
+    pub bearer: Option<String>,
+    pub compression: Option<Compression>,
+    pub progress: Option<Arc<ProgressTracker>>,
+    pub cancel: Option<CancellationToken>,
+}
+
+impl DownloadRequest {
+    pub fn new(url: impl Into<String>, dest: impl Into<PathBuf>) -> Self {
+        Self {
+            url: url.into(),
+            dest: dest.into(),
+            checksum: None,
+            size: None,
+            body: None,
+            bearer: None,
+            compression: None,
+            progress: None,
+            cancel: None,
+        }
+    }
+
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
+    pub fn size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn body(mut self, body: serde_json::Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn bearer_auth(mut self, token: Option<&str>) -> Self {
+        self.bearer = token.map(str::to_string);
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn progress(mut self, progress: &Arc<ProgressTracker>) -> Self {
+        self.progress = Some(progress.clone());
+        self
+    }
+
+    pub fn cancel(mut self, token: &CancellationToken) -> Self {
+        self.cancel = Some(token.clone());
+        self
+    }
+
+    fn file_name(&self) -> String {
+        self.dest
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    fn report_bytes(&self, reported: &AtomicU64, bytes: u64) {
+        reported.fetch_add(bytes, Ordering::Relaxed);
+        if let Some(progress) = &self.progress {
+            progress.add_bytes(bytes);
+        }
+    }
+
+    fn discard_bytes(&self, reported: &AtomicU64) {
+        let bytes = reported.swap(0, Ordering::Relaxed);
+        if let Some(progress) = &self.progress {
+            progress.sub_bytes(bytes);
+        }
+    }
+
+    /// Проверка только что скачанного `.part` по хэшу, посчитанному на лету.
+    fn verify_streamed(&self, path: &Path, hash: Option<StreamingHash>) -> std::io::Result<bool> {
+        if let Some(size) = self.size {
+            if std::fs::metadata(path)?.len() != size {
+                return Ok(false);
+            }
+        }
+        match (&self.checksum, hash) {
+            (Some(checksum), Some(hash)) => Ok(hash.matches(checksum)),
+            _ => self.verify(path),
+        }
+    }
+
+    /// Проверяет файл по размеру и хэшу. Без хэша достаточно того, что файл существует.
+    pub fn verify(&self, path: &Path) -> std::io::Result<bool> {
+        if let Some(size) = self.size {
+            if std::fs::metadata(path)?.len() != size {
+                return Ok(false);
+            }
+        }
+        match &self.checksum {
+            Some(checksum) => checksum.matches(path),
+            None => Ok(path.exists()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    /// Файл уже был на диске и прошёл проверку.
+    Skipped,
+    Downloaded { bytes: u64 },
+}
+
+pub type DownloadResult = Result<DownloadOutcome, DownloadError>;
+
+/// Ответ на условный запрос вместе с валидаторами для следующего.
+#[derive(Debug)]
+pub struct ConditionalResponse {
+    pub bytes: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub struct DownloadEngine {
+    /// Ошибка — прокси из настроек не собрался; запросы не выполняются,
+    /// чтобы трафик не ушёл в обход прокси.
+    client: RwLock<Result<Client, String>>,
+    semaphore: Arc<Semaphore>,
+    /// Отдельный лимит для небольших запросов метаданных: загрузки на паузе
+    /// держат свои разрешения из `semaphore`, а манифесты и списки версий
+    /// при этом должны продолжать работать.
+    metadata_semaphore: Semaphore,
+}
+
+/// HTTP-клиент с прокси из настроек лаунчера.
+pub fn build_client(proxy: &ProxyConfig) -> Result<Client, DownloadError> {
+    let mut builder = Client::builder().user_agent(USER_AGENT);
+    if proxy.enabled {
+        builder = builder.proxy(reqwest_proxy(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
+pub fn engine() -> &'static DownloadEngine {
+    ENGINE.get_or_init(|| {
+        let client = build_client(&launcher_config().proxy).map_err(|e| {
+            log_err!("Не удалось применить настройки прокси, сетевые запросы отключены: {}", e);
+            e.to_string()
+        });
+        DownloadEngine {
+            client: RwLock::new(client),
+            semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS)),
+            metadata_semaphore: Semaphore::new(MAX_CONCURRENT_METADATA),
+        }
+    })
+}
+
+pub fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_os_string();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+pub fn file_digest<D: Digest>(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = D::new();
+    let mut buffer = [0u8; 8192];
+
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(hex_lower(&hasher.finalize()))
+}
+
+fn hex_lower(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn range_start(content_range: Option<&str>) -> Option<u64> {
+    // Content-Range: bytes 100-199/200
+    content_range?
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Ждёт `fut`, но прерывается с `Cancelled`, если токен отменён раньше.
+async fn cancellable<T>(
+    cancel: Option<&CancellationToken>,
+    fut: impl std::future::Future<Output = T>,
+) -> Result<T, DownloadError> {
+    match cancel {
+        Some(token) => tokio::select! {
+            _ = token.cancelled() => Err(DownloadError::Cancelled),
+            value = fut => Ok(value),
+        },
+        None => Ok(fut.await),
+    }
+}
+
+/// Ошибка сети, прошедшая через `StreamReader`, возвращается как `FetchError`,
+/// чтобы её по-прежнему можно было повторить.
+fn from_stream_error(error: std::io::Error) -> DownloadError {
+    if error.get_ref().is_some_and(|inner| inner.is::<reqwest::Error>()) {
+        if let Some(inner) = error.into_inner() {
+            if let Ok(fetch) = inner.downcast::<reqwest::Error>() {
+                return DownloadError::FetchError(*fetch);
+            }
+        }
+        return DownloadError::SystemError("Ошибка чтения ответа".to_string());
+    }
+    DownloadError::IoError(error)
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_BACKOFF)
+}
+
+impl DownloadEngine {
+    fn client(&self) -> Result<Client, DownloadError> {
+        let client = match self.client.read() {
+            Ok(client) => client.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        client.map_err(|e| {
+            DownloadError::SystemError(format!("Прокси из настроек не работает, исправьте его: {}", e))
+        })
+    }
+
+    /// Подменяет клиент после смены настроек прокси. Уже начатые загрузки
+    /// доработают со старым клиентом.
+    pub fn replace_client(&self, client: Client) {
+        match self.client.write() {
+            Ok(mut current) => *current = Ok(client),
+            Err(poisoned) => *poisoned.into_inner() = Ok(client),
+        }
+    }
+
+    fn build_request(&self, request: &DownloadRequest, url: &str) -> Result<RequestBuilder, DownloadError> {
+        let client = self.client()?;
+        let builder = match &request.body {
+            Some(body) => client.post(url).json(body),
+            None => client.get(url),
+        };
+        Ok(match &request.bearer {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        })
+    }
+
+    async fn acquire(&self, cancel: Option<&CancellationToken>) -> Result<SemaphorePermit<'_>, DownloadError> {
+        cancellable(cancel, self.semaphore.acquire())
+            .await?
+            .map_err(|e| DownloadError::SystemError(e.to_string()))
+    }
+
+    async fn acquire_metadata(&self) -> Result<SemaphorePermit<'_>, DownloadError> {
+        self.metadata_semaphore
+            .acquire()
+            .await
+            .map_err(|e| DownloadError::SystemError(e.to_string()))
+    }
+
+    /// Выполняет `op` с повторами и экспоненциальной задержкой для сетевых ошибок, 5xx и 429.
+    async fn with_retries<T, F, Fut>(
+        &self,
+        what: &str,
+        cancel: Option<&CancellationToken>,
+        mut op: F,
+    ) -> Result<T, DownloadError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, DownloadError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Err(e) if e.is_retryable() && attempt < MAX_RETRIES => {
+                    let delay = backoff(attempt);
+                    attempt += 1;
+                    log_info!(
+                        "Повтор {}/{} через {:?}: {} ({})",
+                        attempt,
+                        MAX_RETRIES,
+                        delay,
+                        what,
+                        e
+                    );
+                    cancellable(cancel, tokio::time::sleep(delay)).await?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Выполняет `op` для каждого адреса из `mirror::candidates(url)` по очереди,
+    /// пока один из них не ответит.
+    async fn with_mirrors<T, F, Fut>(&self, url: &str, mut op: F) -> Result<T, DownloadError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: std::future::Future<Output = Result<T, DownloadError>>,
+    {
+        if launcher_config().offline {
+            return Err(DownloadError::Offline);
+        }
+
+        let mut candidates = mirror::candidates(url).into_iter().peekable();
+        while let Some(candidate) = candidates.next() {
+            match op(candidate.clone()).await {
+                Err(e) if mirror::should_fall_back(&e) && candidates.peek().is_some() => {
+                    log_err!("{} недоступен ({}), пробуем следующий адрес", candidate, e);
+                }
+                result => return result,
+            }
+        }
+        Err(DownloadError::SystemError(format!("Нет адреса для {}", url)))
+    }
+
+    /// Скачивает тело ответа целиком в память — для небольших JSON с метаданными.
+    pub async fn fetch_bytes_with_token(&self, url: &str, token: Option<&str>) -> Result<Vec<u8>, DownloadError> {
+        self.with_mirrors(url, |url| async move {
+            self.with_retries(&url, None, || async {
+                let _permit = self.acquire_metadata().await?;
+                let mut builder = self.client()?.get(&url);
+                if let Some(token) = token {
+                    builder = builder.bearer_auth(token);
+                }
+                let response = builder.send().await?.error_for_status()?;
+                Ok(response.bytes().await?.to_vec())
+            })
+            .await
+        })
+        .await
+    }
+
+    /// GET с `If-None-Match`/`If-Modified-Since`. `None` — сервер ответил 304,
+    /// закэшированная копия актуальна.
+    pub async fn fetch_conditional(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<Option<ConditionalResponse>, DownloadError> {
+        self.with_mirrors(url, |url| async move {
+            self.with_retries(&url, None, || async {
+                let _permit = self.acquire_metadata().await?;
+                let mut builder = self.client()?.get(&url);
+                if let Some(etag) = etag {
+                    builder = builder.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = last_modified {
+                    builder = builder.header(IF_MODIFIED_SINCE, last_modified);
+                }
+
+                let response = builder.send().await?;
+                if response.status() == StatusCode::NOT_MODIFIED {
+                    return Ok(None);
+                }
+                let response = response.error_for_status()?;
+                let header = |name: HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string)
+                };
+                let etag = header(ETAG);
+                let last_modified = header(LAST_MODIFIED);
+
+                Ok(Some(ConditionalResponse {
+                    bytes: response.bytes().await?.to_vec(),
+                    etag,
+                    last_modified,
+                }))
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Докачивает `dest.part`: если часть файла уже есть, запрашивает остаток через `Range`.
+    /// Если сервер Range не поддерживает, файл перезаписывается с начала.
+    async fn fetch_part(
+        &self,
+        request: &DownloadRequest,
+        url: &str,
+        part: &Path,
+        reported: &AtomicU64,
+    ) -> Result<(u64, Option<StreamingHash>), DownloadError> {
+        if let Some(parent) = part.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // Сжатый поток нельзя продолжить с середины распакованного файла
+        let offset = match fs::metadata(part).await {
+            Ok(meta) if request.compression.is_none() => meta.len(),
+            _ => 0,
+        };
+
+        let mut builder = self.build_request(request, url)?;
+        if offset > 0 {
+            builder = builder.header(RANGE, format!("bytes={}-", offset));
+        }
+
+        let cancel = request.cancel.as_ref();
+        let mut response = cancellable(cancel, builder.send()).await??;
+
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // .part больше, чем файл на сервере — начинаем заново
+            fs::remove_file(part).await?;
+            response = cancellable(cancel, self.build_request(request, url)?.send()).await??;
+        }
+
+        let response = response.error_for_status()?;
+
+        let resumed = response.status() == StatusCode::PARTIAL_CONTENT
+            && range_start(
+                response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|v| v.to_str().ok()),
+            ) == Some(offset);
+
+        let mut hash = request.checksum.as_ref().map(StreamingHash::new);
+        let mut file = if resumed {
+            log_info!("Докачиваем {:?} с {} байт", part, offset);
+            if let Some(mut state) = hash.take() {
+                // Перечитывание .part целиком блокирует поток, поэтому не в async-воркере
+                let part = part.to_path_buf();
+                let state = tokio::task::spawn_blocking(move || state.update_from_file(&part).map(|_| state)).await??;
+                hash = Some(state);
+            }
+            // Байты из .part прошлого запуска ещё не учтены в прогрессе
+            request.report_bytes(reported, offset.saturating_sub(reported.load(Ordering::Relaxed)));
+            OpenOptions::new().append(true).open(part).await?
+        } else {
+            request.discard_bytes(reported);
+            fs::File::create(part).await?
+        };
+
+        if let Some(compression) = request.compression {
+            return self.decode_part(request, response, compression, file, hash, reported).await;
+        }
+
+        let mut transferred = 0u64;
+        let mut stream = response.bytes_stream();
+        loop {
+            let next = match cancellable(cancel, stream.next()).await {
+                Ok(Some(item)) => item.map_err(DownloadError::from),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+            let chunk = match next {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    // Оставляем уже полученное в .part, чтобы потом докачать: повтор
+                    // берёт смещение для Range из размера файла, поэтому сначала flush
+                    file.flush().await?;
+                    return Err(e);
+                }
+            };
+            if let Some(hash) = &mut hash {
+                hash.update(&chunk);
+            }
+            file.write_all(&chunk).await?;
+            transferred += chunk.len() as u64;
+            request.report_bytes(reported, chunk.len() as u64);
+
+            if let Err(e) = cancellable(cancel, bandwidth().consume(chunk.len())).await {
+                file.flush().await?;
+                return Err(e);
+            }
+        }
+
+        file.flush().await?;
+        file.sync_all().await?;
+
+        Ok((transferred, hash))
+    }
+
+    /// Распаковывает сжатый ответ в `.part` по мере получения.
+    async fn decode_part(
+        &self,
+        request: &DownloadRequest,
+        response: reqwest::Response,
+        compression: Compression,
+        mut file: fs::File,
+        mut hash: Option<StreamingHash>,
+        reported: &AtomicU64,
+    ) -> Result<(u64, Option<StreamingHash>), DownloadError> {
+        // Лимит считается по сжатым байтам из сети, а не по распакованным
+        let stream = response.bytes_stream().then(|item| async move {
+            if let Ok(chunk) = &item {
+                bandwidth().consume(chunk.len()).await;
+            }
+            item
+        });
+        let reader = StreamReader::new(stream.map_err(std::io::Error::other));
+        let mut decoder: Pin<Box<dyn AsyncRead + Send>> = match compression {
+            Compression::Zstd => Box::pin(ZstdDecoder::new(reader)),
+            Compression::Gzip => Box::pin(GzipDecoder::new(reader)),
+        };
+
+        let cancel = request.cancel.as_ref();
+        let mut buffer = vec![0u8; DECODE_BUFFER_SIZE];
+        let mut transferred = 0u64;
+        loop {
+            let read = cancellable(cancel, decoder.read(&mut buffer))
+                .await?
+                .map_err(from_stream_error)?;
+            if read == 0 {
+                break;
+            }
+            if let Some(hash) = &mut hash {
+                hash.update(&buffer[..read]);
+            }
+            file.write_all(&buffer[..read]).await?;
+            transferred += read as u64;
+            request.report_bytes(reported, read as u64);
+        }
+
+        file.flush().await?;
+        file.sync_all().await?;
+
+        Ok((transferred, hash))
+    }
+
+    /// Скачивает файл во временный `.part`, проверяет его и только после этого атомарно
+    /// переименовывает в `dest`. При несовпадении хэша `.part` удаляется и файл скачивается
+    /// заново; если и повторная попытка не прошла проверку — возвращается `Corrupted`
+    /// и загрузка переходит к следующему зеркалу.
+    async fn fetch_verified(&self, request: &DownloadRequest) -> Result<u64, DownloadError> {
+        let part = part_path(&request.dest);
+        let reported = AtomicU64::new(0);
+        let (part, reported) = (&part, &reported);
+
+        self.with_mirrors(&request.url, |url| async move {
+            self.fetch_verified_from(request, &url, part, reported).await
+        })
+        .await
+    }
+
+    async fn fetch_verified_from(
+        &self,
+        request: &DownloadRequest,
+        url: &str,
+        part: &Path,
+        reported: &AtomicU64,
+    ) -> Result<u64, DownloadError> {
+        let mut transferred = 0u64;
+
+        for attempt in 1..=MAX_VERIFY_ATTEMPTS {
+            let (bytes, hash) = self
+                .with_retries(url, request.cancel.as_ref(), || async {
+                    let _permit = self.acquire(request.cancel.as_ref()).await?;
+                    self.fetch_part(request, url, part, reported).await
+                })
+                .await?;
+            transferred += bytes;
+
+            if request.verify_streamed(part, hash)? {
+                fs::rename(part, &request.dest).await?;
+                return Ok(transferred);
+            }
+
+            log_err!(
+                "Контрольная сумма не совпала ({}/{}): {:?}",
+                attempt,
+                MAX_VERIFY_ATTEMPTS,
+                request.dest
+            );
+            request.discard_bytes(reported);
+            fs::remove_file(part).await?;
+        }
+
+        Err(DownloadError::Corrupted(request.dest.to_string_lossy().to_string()))
+    }
+
+    /// Скачивает один файл. Уже существующий файл, прошедший проверку, не трогается.
+    pub async fn download(&self, request: &DownloadRequest) -> DownloadResult {
+        let result = self.download_inner(request).await;
+        if let Some(progress) = &request.progress {
+            progress.file_done(&request.file_name());
+        }
+        result
+    }
+
+    async fn download_inner(&self, request: &DownloadRequest) -> DownloadResult {
+        if request.cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            return Err(DownloadError::Cancelled);
+        }
+
+        if request.dest.exists() {
+            // Хэш существующего файла считается под тем же лимитом, что и загрузки,
+            // и в блокирующем пуле: иначе тысячи задач download_all займут все async-потоки
+            let valid = {
+                let _permit = self.acquire(request.cancel.as_ref()).await?;
+                let check = request.clone();
+                tokio::task::spawn_blocking(move || check.verify(&check.dest)).await??
+            };
+            if valid {
+                if let Some(progress) = &request.progress {
+                    let size = match request.size {
+                        Some(size) => size,
+                        None => fs::metadata(&request.dest).await?.len(),
+                    };
+                    progress.add_bytes(size);
+                }
+                return Ok(DownloadOutcome::Skipped);
+            }
+            log_info!("Файл повреждён, скачиваем заново: {:?}", request.dest);
+        }
+
+        let bytes = self.fetch_verified(request).await.map_err(|e| {
+            if !matches!(e, DownloadError::Cancelled) {
+                log_err!("Ошибка при скачивании {}: {}", request.url, e);
+            }
+            e
+        })?;
+
+        Ok(DownloadOutcome::Downloaded { bytes })
+    }
+
+    /// Скачивает набор файлов параллельно (в пределах общего лимита) и возвращает
+    /// результат для каждого запроса в исходном порядке.
+    pub async fn download_all(
+        &'static self,
+        requests: Vec<DownloadRequest>,
+    ) -> Vec<(DownloadRequest, DownloadResult)> {
+        let tasks: Vec<_> = requests
+            .iter()
+            .cloned()
+            .map(|request| tokio::spawn(async move { self.download(&request).await }))
+            .collect();
+
+        let results = futures::future::join_all(tasks).await;
+
+        requests
+            .into_iter()
+            .zip(results)
+            .map(|(request, result)| (request, result.unwrap_or_else(|e| Err(e.into()))))
+            .collect()
+    }
+}
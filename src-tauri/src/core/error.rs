@@ -0,0 +1,56 @@
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    #[error("Не удалось получить файл от сервера: {0}")]
+    FetchError(#[from] reqwest::Error),
+
+    #[error("Не удалось получить размер файла из заголовков")]
+    ContentLengthError,
+
+    #[error("Ошибка файловой системы: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Ошибка JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Системная ошибка: {0}")]
+    SystemError(String),
+
+    #[error("Ошибка выполнения задачи: {0}")]
+    JoinError(#[from] tokio::task::JoinError),
+
+    #[error("Файл повреждён после повторного скачивания: {0}")]
+    Corrupted(String),
+
+    #[error("Операция отменена")]
+    Cancelled,
+
+    #[error("Подпись манифеста не прошла проверку: {0}")]
+    Signature(String),
+
+    #[error("Включён автономный режим, сеть не используется")]
+    Offline,
+}
+
+impl DownloadError {
+    /// Сетевые сбои, 5xx и 429 имеет смысл повторить, остальное — нет.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            DownloadError::FetchError(e) => match e.status() {
+                Some(status) => status.is_server_error() || status.as_u16() == 429,
+                None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Serialize for DownloadError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::core::engine::{Checksum, DownloadRequest};
+use crate::core::fs::write_atomic;
+use crate::log_err;
+
+const INDEX_FILE: &str = ".limacina/fingerprints.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    size: u64,
+    /// Время изменения в миллисекундах с эпохи Unix.
+    mtime: u64,
+    /// Хэш вместе с алгоритмом, например `sha256:…`.
+    hash: String,
+}
+
+/// Локальный индекс `путь -> (размер, mtime, хэш)`. Файл, у которого размер и
+/// mtime совпадают с записью, считается проверенным без повторного хэширования.
+#[derive(Debug)]
+pub struct FingerprintIndex {
+    path: PathBuf,
+    entries: HashMap<String, Fingerprint>,
+    dirty: bool,
+}
+
+fn checksum_key(checksum: &Checksum) -> String {
+    match checksum {
+        Checksum::Sha1(hash) => format!("sha1:{}", hash.to_ascii_lowercase()),
+        Checksum::Sha256(hash) => format!("sha256:{}", hash.to_ascii_lowercase()),
+        Checksum::Md5(hash) => format!("md5:{}", hash.to_ascii_lowercase()),
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    Some((metadata.len(), mtime))
+}
+
+impl FingerprintIndex {
+    /// Загружает индекс из папки лаунчера. Повреждённый индекс просто начинается заново.
+    pub fn load(base: &Path) -> Self {
+        let path = base.join(INDEX_FILE);
+        let entries = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Проверяет файл запроса. С `force` хэш пересчитывается в любом случае.
+    pub fn verify(&mut self, relative: &str, request: &DownloadRequest, force: bool) -> bool {
+        let Some(checksum) = &request.checksum else {
+            return request.verify(&request.dest).unwrap_or(false);
+        };
+        let Some((size, mtime)) = file_stamp(&request.dest) else {
+            self.remove(relative);
+            return false;
+        };
+        if request.size.is_some_and(|expected| expected != size) {
+            return false;
+        }
+
+        let key = checksum_key(checksum);
+        if !force {
+            if let Some(entry) = self.entries.get(relative) {
+                if entry.size == size && entry.mtime == mtime && entry.hash == key {
+                    return true;
+                }
+            }
+        }
+
+        let valid = checksum.matches(&request.dest).unwrap_or(false);
+        if valid {
+            self.insert(relative, size, mtime, key);
+        } else {
+            self.remove(relative);
+        }
+        valid
+    }
+
+    /// Проверка только по индексу, без чтения файла: `true`, если размер и mtime
+    /// совпадают с записью, а записанный хэш — с ожидаемым. Файл без хэша в
+    /// манифесте сверяется так же, как в `verify`: по размеру или наличию.
+    pub fn is_current(&self, relative: &str, request: &DownloadRequest) -> bool {
+        let Some(checksum) = &request.checksum else {
+            return request.verify(&request.dest).unwrap_or(false);
+        };
+        let Some((size, mtime)) = file_stamp(&request.dest) else {
+            return false;
+        };
+        self.entries.get(relative).is_some_and(|entry| {
+            entry.size == size && entry.mtime == mtime && entry.hash == checksum_key(checksum)
+        })
+    }
+
+    /// Есть ли файл в индексе, т.е. ставил ли его лаунчер.
+    pub fn contains(&self, relative: &str) -> bool {
+        self.entries.contains_key(relative)
+    }
+
+    /// Запоминает только что скачанный и проверенный движком файл.
+    pub fn record(&mut self, relative: &str, request: &DownloadRequest) {
+        let (Some(checksum), Some((size, mtime))) = (&request.checksum, file_stamp(&request.dest)) else {
+            return;
+        };
+        self.insert(relative, size, mtime, checksum_key(checksum));
+    }
+
+    pub fn remove(&mut self, relative: &str) {
+        if self.entries.remove(relative).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    fn insert(&mut self, relative: &str, size: u64, mtime: u64, hash: String) {
+        let fingerprint = Fingerprint { size, mtime, hash };
+        if self.entries.get(relative) != Some(&fingerprint) {
+            self.entries.insert(relative.to_string(), fingerprint);
+            self.dirty = true;
+        }
+    }
+
+    /// Сохраняет индекс через временный файл, чтобы обрыв не оставил его битым.
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        let result = serde_json::to_vec(&self.entries)
+            .map_err(std::io::Error::from)
+            .and_then(|data| write_atomic(&self.path, &data));
+
+        match result {
+            Ok(()) => self.dirty = false,
+            Err(e) => {
+                log_err!("Не удалось сохранить индекс файлов {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::file_digest;
+    use sha2::Sha256;
+    use std::time::{Duration, SystemTime};
+
+    const FILE: &str = "mods/a.jar";
+
+    /// Файл сборки, уже проверенный и записанный в индекс.
+    fn setup(name: &str) -> (PathBuf, FingerprintIndex, DownloadRequest) {
+        let base = std::env::temp_dir().join(format!("limacina-fingerprint-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let path = base.join(FILE);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"original").unwrap();
+
+        let request = DownloadRequest::new("", path.clone())
+            .checksum(Checksum::Sha256(file_digest::<Sha256>(&path).unwrap()))
+            .size(8);
+        let mut index = FingerprintIndex::load(&base);
+        assert!(index.verify(FILE, &request, false));
+        assert!(index.is_current(FILE, &request));
+        (base, index, request)
+    }
+
+    /// Подменяет содержимое файла, сохраняя размер и mtime.
+    fn tamper(path: &Path, data: &[u8], mtime: Option<SystemTime>) {
+        let modified = std::fs::metadata(path).unwrap().modified().unwrap();
+        std::fs::write(path, data).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(mtime.unwrap_or(modified)).unwrap();
+    }
+
+    #[test]
+    fn matching_stamp_skips_hashing() {
+        let (base, mut index, request) = setup("stamp");
+        tamper(&request.dest, b"tampered", None);
+
+        assert!(index.is_current(FILE, &request));
+        assert!(index.verify(FILE, &request, false));
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn force_verify_ignores_index() {
+        let (base, mut index, request) = setup("force");
+        tamper(&request.dest, b"tampered", None);
+
+        assert!(!index.verify(FILE, &request, true));
+        assert!(!index.contains(FILE));
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn changed_mtime_invalidates_entry() {
+        let (base, mut index, request) = setup("mtime");
+        let later = SystemTime::now() + Duration::from_secs(60);
+        tamper(&request.dest, b"tampered", Some(later));
+
+        assert!(!index.is_current(FILE, &request));
+        assert!(!index.verify(FILE, &request, false));
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn changed_size_invalidates_entry() {
+        let (base, mut index, request) = setup("size");
+        tamper(&request.dest, b"original!", None);
+
+        assert!(!index.is_current(FILE, &request));
+        assert!(!index.verify(FILE, &request, false));
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn index_survives_reload() {
+        let (base, mut index, request) = setup("reload");
+        index.save();
+
+        assert!(FingerprintIndex::load(&base).is_current(FILE, &request));
+        let _ = std::fs::remove_dir_all(base);
+    }
+}
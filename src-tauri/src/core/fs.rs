@@ -0,0 +1,12 @@
+use std::path::Path;
+
+/// Пишет файл через временный `<имя>.tmp` и переименование, чтобы обрыв не оставил его битым.
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
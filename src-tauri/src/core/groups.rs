@@ -0,0 +1,85 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::core::downloader::{fetch_manifest, get_base_dir};
+use crate::core::error::DownloadError;
+use crate::core::fs::write_atomic;
+use crate::core::manifest::{normalize_path, ModpackManifest};
+use crate::log_info;
+
+/// Выбор игрока хранится в папке установки: `id группы -> включена`.
+const CHOICES_FILE: &str = ".limacina/mod_groups.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModGroupState {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+    pub files: Vec<String>,
+}
+
+fn choices_path(base: &Path) -> PathBuf {
+    base.join(CHOICES_FILE)
+}
+
+pub fn load_choices(base: &Path) -> HashMap<String, bool> {
+    std::fs::read(choices_path(base))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_choices(base: &Path, choices: &HashMap<String, bool>) -> Result<(), DownloadError> {
+    write_atomic(&choices_path(base), &serde_json::to_vec_pretty(choices)?)?;
+    Ok(())
+}
+
+pub fn group_states(manifest: &ModpackManifest, choices: &HashMap<String, bool>) -> Vec<ModGroupState> {
+    manifest
+        .groups
+        .iter()
+        .map(|group| ModGroupState {
+            id: group.id.clone(),
+            name: group.name.clone(),
+            description: group.description.clone(),
+            enabled: choices.get(&group.id).copied().unwrap_or(group.default_enabled),
+            files: group.files.clone(),
+        })
+        .collect()
+}
+
+/// Файлы, которые входят только в выключенные группы. Файл, общий с
+/// включённой группой, остаётся.
+pub fn disabled_files(manifest: &ModpackManifest, choices: &HashMap<String, bool>) -> HashSet<String> {
+    let mut enabled = HashSet::new();
+    let mut disabled = HashSet::new();
+
+    for group in group_states(manifest, choices) {
+        let target = if group.enabled { &mut enabled } else { &mut disabled };
+        target.extend(group.files.iter().map(|file| normalize_path(file)));
+    }
+
+    disabled.retain(|file| !enabled.contains(file));
+    disabled
+}
+
+#[tauri::command]
+pub async fn list_mod_groups() -> Result<Vec<ModGroupState>, DownloadError> {
+    let manifest = fetch_manifest().await?;
+    let base = PathBuf::from(get_base_dir()?);
+    Ok(group_states(&manifest, &load_choices(&base)))
+}
+
+/// Сохраняет выбор игрока; файлы добавятся или удалятся при следующей синхронизации.
+#[tauri::command]
+pub fn set_mod_group(group_id: String, enabled: bool) -> Result<(), DownloadError> {
+    let base = PathBuf::from(get_base_dir()?);
+    let mut choices = load_choices(&base);
+    choices.insert(group_id.clone(), enabled);
+    save_choices(&base, &choices)?;
+
+    log_info!("Группа модов {}: {}", group_id, if enabled { "включена" } else { "выключена" });
+    Ok(())
+}
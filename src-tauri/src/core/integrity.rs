@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use walkdir::WalkDir;
+
+use crate::core::config::{launcher_config, StaleFilesMode};
+use crate::core::downloader::{
+    download_request, fetch_manifest, file_action, get_base_dir, sync_modpack, FileAction, SyncReport,
+};
+use crate::core::error::DownloadError;
+use crate::core::fingerprint::FingerprintIndex;
+use crate::core::groups::{disabled_files, load_choices};
+use crate::core::manifest::{normalize_path, FilePolicy};
+use crate::core::stale::{build_allow_list, is_mod_jar, StaleScope};
+
+/// Папки, которые сверяются с манифестом перед запуском.
+const CHECKED_DIRS: &[&str] = &["mods/", "config/"];
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Файла из манифеста нет.
+    Missing,
+    /// Файл отличается от версии на сервере.
+    Modified,
+    /// Файла нет в манифесте (или он должен быть удалён).
+    Unexpected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityIssue {
+    pub path: String,
+    pub kind: IssueKind,
+}
+
+fn is_checked(path: &str) -> bool {
+    CHECKED_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Сверяет `mods/` и `config/` с манифестом сервера. Файлы с политикой
+/// `create_only` и `optional` игрок вправе менять, они не проверяются.
+pub async fn check_integrity() -> Result<Vec<IntegrityIssue>, DownloadError> {
+    let sync = launcher_config().sync;
+    let manifest = fetch_manifest().await?;
+    let core = PathBuf::from(get_base_dir()?);
+
+    let mut fingerprints = FingerprintIndex::load(&core);
+    let disabled = disabled_files(&manifest, &load_choices(&core));
+    let mut issues = Vec::new();
+
+    for file in &manifest.files {
+        let path = file.normalized_path();
+        if !is_checked(&path) || matches!(file.policy, FilePolicy::CreateOnly | FilePolicy::Optional) {
+            continue;
+        }
+
+        let file_path = file.local_path(&core)?;
+        let kind = match file_action(file, &file_path, &disabled) {
+            FileAction::Remove => IssueKind::Unexpected,
+            FileAction::Leave => continue,
+            FileAction::Sync if !file_path.exists() => IssueKind::Missing,
+            FileAction::Sync => {
+                let request = download_request(&sync, file_path, file);
+                if fingerprints.verify(&path, &request, false) {
+                    continue;
+                }
+                IssueKind::Modified
+            }
+        };
+        issues.push(IntegrityIssue { path, kind });
+    }
+    fingerprints.save();
+
+    // Посторонние jar в mods/, кроме разрешённых allow-list'ом
+    let known: HashSet<String> = manifest.files.iter().map(|f| f.normalized_path()).collect();
+    let allow_list = build_allow_list(sync.keep.iter().chain(&manifest.keep))?;
+    let mods_dir = core.join("mods");
+    if mods_dir.is_dir() {
+        for entry in WalkDir::new(&mods_dir).into_iter().filter_map(|e| e.ok()) {
+            let Ok(relative) = entry.path().strip_prefix(&core) else {
+                continue;
+            };
+            let relative = normalize_path(&relative.to_string_lossy());
+
+            if entry.file_type().is_file()
+                && is_mod_jar(&relative)
+                && !known.contains(&relative)
+                && !allow_list.is_match(&relative)
+            {
+                issues.push(IntegrityIssue {
+                    path: relative,
+                    kind: IssueKind::Unexpected,
+                });
+            }
+        }
+    }
+
+    issues.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(issues)
+}
+
+#[tauri::command]
+pub async fn check_modpack_integrity() -> Result<Vec<IntegrityIssue>, DownloadError> {
+    check_integrity().await
+}
+
+/// Восстановление по кнопке: полная перепроверка хэшей и синхронизация.
+/// Лишние файлы, включая посторонние моды, уходят в карантин, даже если в
+/// настройках их удаление выключено.
+#[tauri::command]
+pub async fn repair_modpack(app: AppHandle, operation_id: Option<String>) -> Result<SyncReport, DownloadError> {
+    let stale_files = match launcher_config().sync.stale_files {
+        StaleFilesMode::Keep => StaleFilesMode::Quarantine,
+        mode => mode,
+    };
+    sync_modpack(&app, operation_id, true, stale_files, StaleScope::WithForeignMods).await
+}
@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::core::engine::{Checksum, Compression};
+use crate::core::error::DownloadError;
+
+/// Последняя версия схемы манифеста, которую понимает лаунчер.
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilePolicy {
+    /// Файл всегда приводится к версии с сервера.
+    #[default]
+    Overwrite,
+    /// Файл скачивается, только если его нет; правки пользователя не затираются.
+    CreateOnly,
+    /// Как `Overwrite`, но ошибка скачивания не прерывает синхронизацию.
+    Optional,
+    /// Файл должен быть удалён у клиента.
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Путь относительно папки лаунчера.
+    pub path: String,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    /// Только для манифестов старого формата.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// Прямая ссылка на файл; без неё используется транспорт из настроек.
+    pub url: Option<String>,
+    /// Сжатие, в котором отдаётся файл. Без прямой ссылки к пути добавляется
+    /// расширение (`.zst`, `.gz`).
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    #[serde(default)]
+    pub policy: FilePolicy,
+    /// Бинарные патчи до этой версии файла с известных предыдущих версий.
+    #[serde(default)]
+    pub patches: Vec<FilePatch>,
+    /// Слой, из которого пришла запись (заполняется при слиянии манифестов).
+    #[serde(default, skip_deserializing)]
+    pub layer: String,
+}
+
+impl ManifestFile {
+    pub fn checksum(&self) -> Option<Checksum> {
+        match (&self.sha256, &self.md5) {
+            (Some(sha256), _) => Some(Checksum::Sha256(sha256.clone())),
+            (None, Some(md5)) => Some(Checksum::Md5(md5.clone())),
+            (None, None) => None,
+        }
+    }
+
+    /// Путь файла внутри `base`. Абсолютные пути и `..` отвергаются,
+    /// чтобы манифест не мог писать за пределы папки лаунчера.
+    pub fn local_path(&self, base: &Path) -> Result<PathBuf, DownloadError> {
+        safe_join(base, &self.path)
+    }
+
+    /// Путь в виде `dir/file` — для сравнения с файлами на диске.
+    pub fn normalized_path(&self) -> String {
+        normalize_path(&self.path)
+    }
+}
+
+pub fn safe_join(base: &Path, relative: &str) -> Result<PathBuf, DownloadError> {
+    let path = Path::new(relative);
+    let is_safe = path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+
+    if relative.is_empty() || !is_safe {
+        return Err(DownloadError::SystemError(format!(
+            "Недопустимый путь в манифесте: {}",
+            relative
+        )));
+    }
+    Ok(base.join(path))
+}
+
+pub fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Патч в формате `zstd --patch-from`: из файла с хэшем `from_sha256`
+/// получается текущая версия файла.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePatch {
+    pub from_sha256: String,
+    pub url: String,
+    pub size: Option<u64>,
+    /// Хэш самого патча.
+    pub sha256: Option<String>,
+}
+
+/// Архив всей сборки (zip с путями относительно папки лаунчера) для первой установки.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackArchive {
+    pub url: String,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+}
+
+/// Необязательная группа модов, которую игрок может включить или выключить.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModGroup {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub default_enabled: bool,
+    /// Пути файлов группы, как в `files`.
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModpackManifest {
+    pub version: u32,
+    /// Имя слоя для отчёта, например `base` или имя сервера.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub files: Vec<ManifestFile>,
+    /// Папки, кроме `mods` и `config`, где лишние файлы удаляются.
+    #[serde(default)]
+    pub managed_dirs: Vec<String>,
+    /// Glob-шаблоны файлов, которые сервер разрешает держать в управляемых папках.
+    #[serde(default)]
+    pub keep: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<ModGroup>,
+    #[serde(default)]
+    pub archive: Option<PackArchive>,
+}
+
+/// Запись старого плоского манифеста: либо просто MD5, либо объект с прямой ссылкой.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum LegacyEntry {
+    Hash(String),
+    File {
+        #[serde(alias = "md5")]
+        hash: String,
+        url: Option<String>,
+    },
+}
+
+impl ModpackManifest {
+    /// Разбирает манифест новой схемы или старый формат `путь -> md5`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, DownloadError> {
+        let value: serde_json::Value = serde_json::from_slice(bytes)?;
+        // Новая схема узнаётся по числовому полю `version`, иначе это старый формат
+        let manifest = if value.get("version").is_some_and(|v| v.is_u64()) {
+            serde_json::from_value::<ModpackManifest>(value)?
+        } else {
+            Self::from_legacy(serde_json::from_value(value)?)
+        };
+
+        if manifest.version > MANIFEST_VERSION {
+            return Err(DownloadError::SystemError(format!(
+                "Версия манифеста {} не поддерживается (максимум {}), обновите лаунчер",
+                manifest.version, MANIFEST_VERSION
+            )));
+        }
+        Ok(manifest)
+    }
+
+    fn from_legacy(entries: HashMap<String, LegacyEntry>) -> Self {
+        let files = entries
+            .into_iter()
+            .map(|(path, entry)| {
+                let (md5, url) = match entry {
+                    LegacyEntry::Hash(hash) => (hash, None),
+                    LegacyEntry::File { hash, url } => (hash, url),
+                };
+                ManifestFile {
+                    path,
+                    size: None,
+                    sha256: None,
+                    md5: Some(md5),
+                    url,
+                    compression: None,
+                    policy: FilePolicy::Overwrite,
+                    patches: Vec::new(),
+                    layer: String::new(),
+                }
+            })
+            .collect();
+
+        Self {
+            version: 0,
+            name: None,
+            files,
+            managed_dirs: Vec::new(),
+            keep: Vec::new(),
+            groups: Vec::new(),
+            archive: None,
+        }
+    }
+
+    /// Сливает манифесты по порядку: базовый, затем оверлеи. Запись оверлея
+    /// целиком заменяет запись с тем же путём; запись с `delete` убирает файл из
+    /// сборки и удаляет его у игрока. Группы заменяются по `id`, папки и
+    /// allow-list объединяются.
+    pub fn merge(layers: Vec<(String, ModpackManifest)>) -> ModpackManifest {
+        let mut merged = ModpackManifest {
+            version: 0,
+            name: None,
+            files: Vec::new(),
+            managed_dirs: Vec::new(),
+            keep: Vec::new(),
+            groups: Vec::new(),
+            archive: None,
+        };
+        let mut file_index: HashMap<String, usize> = HashMap::new();
+        let mut group_index: HashMap<String, usize> = HashMap::new();
+
+        for (layer, manifest) in layers {
+            merged.version = merged.version.max(manifest.version);
+
+            for mut file in manifest.files {
+                file.layer = layer.clone();
+                match file_index.get(&file.normalized_path()) {
+                    Some(&i) => merged.files[i] = file,
+                    None => {
+                        file_index.insert(file.normalized_path(), merged.files.len());
+                        merged.files.push(file);
+                    }
+                }
+            }
+
+            for group in manifest.groups {
+                match group_index.get(&group.id) {
+                    Some(&i) => merged.groups[i] = group,
+                    None => {
+                        group_index.insert(group.id.clone(), merged.groups.len());
+                        merged.groups.push(group);
+                    }
+                }
+            }
+
+            // Берётся архив первого слоя, где он есть; остальное докачивается пофайлово
+            if merged.archive.is_none() {
+                merged.archive = manifest.archive;
+            }
+            merged.managed_dirs.extend(manifest.managed_dirs);
+            merged.keep.extend(manifest.keep);
+        }
+
+        merged.managed_dirs.sort();
+        merged.managed_dirs.dedup();
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(manifest: &'a ModpackManifest, path: &str) -> &'a ManifestFile {
+        manifest.files.iter().find(|f| f.path == path).unwrap()
+    }
+
+    #[test]
+    fn parses_legacy_md5_map() {
+        let manifest = ModpackManifest::parse(br#"{"mods/a.jar":"aaa","config/b.cfg":"bbb"}"#).unwrap();
+
+        assert_eq!(manifest.version, 0);
+        assert_eq!(manifest.files.len(), 2);
+        let file = find(&manifest, "mods/a.jar");
+        assert_eq!(file.md5.as_deref(), Some("aaa"));
+        assert_eq!(file.url, None);
+        assert_eq!(file.policy, FilePolicy::Overwrite);
+    }
+
+    #[test]
+    fn parses_legacy_entries_with_url() {
+        let manifest = ModpackManifest::parse(
+            br#"{"mods/a.jar":{"hash":"aaa","url":"https://example.com/a.jar"},"mods/b.jar":{"md5":"bbb"}}"#,
+        )
+        .unwrap();
+
+        let file = find(&manifest, "mods/a.jar");
+        assert_eq!(file.md5.as_deref(), Some("aaa"));
+        assert_eq!(file.url.as_deref(), Some("https://example.com/a.jar"));
+        assert_eq!(find(&manifest, "mods/b.jar").md5.as_deref(), Some("bbb"));
+    }
+
+    #[test]
+    fn parses_current_schema() {
+        let manifest = ModpackManifest::parse(
+            br#"{"version":1,"files":[{"path":"mods/a.jar","size":3,"sha256":"abc","url":null,"policy":"create_only"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.version, MANIFEST_VERSION);
+        let file = find(&manifest, "mods/a.jar");
+        assert_eq!(file.sha256.as_deref(), Some("abc"));
+        assert_eq!(file.policy, FilePolicy::CreateOnly);
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let json = format!(r#"{{"version":{},"files":[]}}"#, MANIFEST_VERSION + 1);
+        assert!(ModpackManifest::parse(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn safe_join_accepts_relative_paths() {
+        let base = Path::new("base");
+        assert_eq!(safe_join(base, "mods/a.jar").unwrap(), base.join("mods/a.jar"));
+        assert_eq!(safe_join(base, "./config/b.cfg").unwrap(), base.join("./config/b.cfg"));
+    }
+
+    #[test]
+    fn safe_join_rejects_parent_dirs() {
+        let base = Path::new("base");
+        assert!(safe_join(base, "../evil.jar").is_err());
+        assert!(safe_join(base, "mods/../../evil.jar").is_err());
+    }
+
+    #[test]
+    fn safe_join_rejects_absolute_paths() {
+        let base = Path::new("base");
+        assert!(safe_join(base, "/etc/passwd").is_err());
+        #[cfg(windows)]
+        assert!(safe_join(base, r"C:\Windows\evil.dll").is_err());
+    }
+
+    #[test]
+    fn safe_join_rejects_empty_path() {
+        assert!(safe_join(Path::new("base"), "").is_err());
+    }
+
+    fn layer(json: &str) -> ModpackManifest {
+        ModpackManifest::parse(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn merge_overlay_replaces_file_with_same_path() {
+        let merged = ModpackManifest::merge(vec![
+            (
+                "base".to_string(),
+                layer(r#"{"version":1,"files":[{"path":"mods/a.jar","size":1,"sha256":"old","url":null},{"path":"mods/b.jar","size":1,"sha256":"b","url":null}]}"#),
+            ),
+            (
+                "server".to_string(),
+                layer(r#"{"version":1,"files":[{"path":"mods\\a.jar","size":2,"sha256":"new","url":null}]}"#),
+            ),
+        ]);
+
+        assert_eq!(merged.files.len(), 2);
+        let file = &merged.files[0];
+        assert_eq!(file.sha256.as_deref(), Some("new"));
+        assert_eq!(file.size, Some(2));
+        assert_eq!(file.layer, "server");
+        assert_eq!(merged.files[1].layer, "base");
+    }
+
+    #[test]
+    fn merge_overlay_delete_overrides_base_file() {
+        let merged = ModpackManifest::merge(vec![
+            (
+                "base".to_string(),
+                layer(r#"{"version":1,"files":[{"path":"mods/a.jar","size":1,"sha256":"a","url":null}]}"#),
+            ),
+            (
+                "server".to_string(),
+                layer(r#"{"version":1,"files":[{"path":"mods/a.jar","size":null,"sha256":null,"url":null,"policy":"delete"}]}"#),
+            ),
+        ]);
+
+        assert_eq!(merged.files.len(), 1);
+        assert_eq!(merged.files[0].policy, FilePolicy::Delete);
+        assert_eq!(merged.files[0].layer, "server");
+    }
+
+    #[test]
+    fn merge_replaces_groups_and_unions_dirs() {
+        let merged = ModpackManifest::merge(vec![
+            (
+                "base".to_string(),
+                layer(r#"{"version":1,"files":[],"managed_dirs":["shaderpacks"],"keep":["config/*.local"],"groups":[{"id":"hd","name":"Base","files":[]}]}"#),
+            ),
+            (
+                "server".to_string(),
+                layer(r#"{"version":1,"files":[],"managed_dirs":["shaderpacks","resourcepacks"],"groups":[{"id":"hd","name":"Server","files":[]}]}"#),
+            ),
+        ]);
+
+        assert_eq!(merged.groups.len(), 1);
+        assert_eq!(merged.groups[0].name, "Server");
+        assert_eq!(merged.managed_dirs, vec!["resourcepacks", "shaderpacks"]);
+        assert_eq!(merged.keep, vec!["config/*.local"]);
+    }
+}
@@ -0,0 +1,147 @@
+use reqwest::Url;
+
+use crate::core::config::{launcher_config, MirrorConfig, MirrorPreset};
+use crate::core::error::DownloadError;
+
+const BMCLAPI: &str = "https://bmclapi2.bangbang93.com";
+
+/// Хост официального сервера -> адрес того же содержимого на BMCLAPI.
+const BMCLAPI_HOSTS: &[(&str, &str)] = &[
+    ("launchermeta.mojang.com", BMCLAPI),
+    ("piston-meta.mojang.com", BMCLAPI),
+    ("piston-data.mojang.com", BMCLAPI),
+    ("launcher.mojang.com", BMCLAPI),
+    ("resources.download.minecraft.net", "https://bmclapi2.bangbang93.com/assets"),
+    ("libraries.minecraft.net", "https://bmclapi2.bangbang93.com/maven"),
+    ("meta.fabricmc.net", "https://bmclapi2.bangbang93.com/fabric-meta"),
+    ("maven.fabricmc.net", "https://bmclapi2.bangbang93.com/maven"),
+    ("maven.minecraftforge.net", "https://bmclapi2.bangbang93.com/maven"),
+];
+
+fn preset_mirror(preset: MirrorPreset, host: &str) -> Option<&'static str> {
+    match preset {
+        MirrorPreset::Official => None,
+        MirrorPreset::Bmclapi => BMCLAPI_HOSTS
+            .iter()
+            .find(|(official, _)| *official == host)
+            .map(|(_, mirror)| *mirror),
+    }
+}
+
+fn rebase(base: &str, url: &Url) -> String {
+    let mut rebased = format!("{}{}", base.trim_end_matches('/'), url.path());
+    if let Some(query) = url.query() {
+        rebased.push('?');
+        rebased.push_str(query);
+    }
+    rebased
+}
+
+/// Адреса, по которым пробуется скачать `url`, в порядке очереди.
+pub fn candidates(url: &str) -> Vec<String> {
+    resolve(&launcher_config().mirrors, url)
+}
+
+fn resolve(config: &MirrorConfig, url: &str) -> Vec<String> {
+    let Ok(parsed) = Url::parse(url) else {
+        return vec![url.to_string()];
+    };
+    let Some(host) = parsed.host_str() else {
+        return vec![url.to_string()];
+    };
+
+    if let Some(base) = &config.override_url {
+        return vec![rebase(&format!("{}/{}", base.trim_end_matches('/'), host), &parsed)];
+    }
+
+    let mut urls: Vec<String> = config
+        .hosts
+        .get(host)
+        .into_iter()
+        .flatten()
+        .map(|base| rebase(base, &parsed))
+        .collect();
+    urls.extend(preset_mirror(config.preset, host).map(|base| rebase(base, &parsed)));
+    urls.push(url.to_string());
+    urls.dedup();
+    urls
+}
+
+/// Ошибки, после которых есть смысл попробовать следующее зеркало:
+/// сервер недоступен, не отдал файл или отдал не тот.
+pub fn should_fall_back(error: &DownloadError) -> bool {
+    matches!(
+        error,
+        DownloadError::FetchError(_) | DownloadError::ContentLengthError | DownloadError::Corrupted(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASSET: &str = "https://resources.download.minecraft.net/ab/abcdef?v=1";
+
+    #[test]
+    fn official_preset_keeps_original_url() {
+        assert_eq!(resolve(&MirrorConfig::default(), ASSET), vec![ASSET]);
+    }
+
+    #[test]
+    fn custom_hosts_go_before_preset_and_official() {
+        let mut config = MirrorConfig {
+            preset: MirrorPreset::Bmclapi,
+            ..MirrorConfig::default()
+        };
+        config.hosts.insert(
+            "resources.download.minecraft.net".to_string(),
+            vec!["https://one.example/".to_string(), "https://two.example/assets".to_string()],
+        );
+
+        assert_eq!(
+            resolve(&config, ASSET),
+            vec![
+                "https://one.example/ab/abcdef?v=1",
+                "https://two.example/assets/ab/abcdef?v=1",
+                "https://bmclapi2.bangbang93.com/assets/ab/abcdef?v=1",
+                ASSET,
+            ]
+        );
+    }
+
+    #[test]
+    fn preset_skips_unknown_hosts() {
+        let config = MirrorConfig {
+            preset: MirrorPreset::Bmclapi,
+            ..MirrorConfig::default()
+        };
+        let url = "https://example.com/pack/mods/a.jar";
+        assert_eq!(resolve(&config, url), vec![url]);
+    }
+
+    #[test]
+    fn override_url_replaces_all_candidates() {
+        let mut config = MirrorConfig {
+            preset: MirrorPreset::Bmclapi,
+            override_url: Some("http://127.0.0.1:8080/".to_string()),
+            ..MirrorConfig::default()
+        };
+        config
+            .hosts
+            .insert("resources.download.minecraft.net".to_string(), vec!["https://one.example".to_string()]);
+
+        assert_eq!(
+            resolve(&config, ASSET),
+            vec!["http://127.0.0.1:8080/resources.download.minecraft.net/ab/abcdef?v=1"]
+        );
+    }
+
+    #[test]
+    fn unparsable_url_is_returned_as_is() {
+        let config = MirrorConfig {
+            override_url: Some("http://127.0.0.1:8080".to_string()),
+            ..MirrorConfig::default()
+        };
+        assert_eq!(resolve(&config, "not a url"), vec!["not a url"]);
+    }
+}
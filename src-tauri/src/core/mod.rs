@@ -1 +1,20 @@
-pub mod downloader;
\ No newline at end of file
+pub mod archive;
+pub mod bandwidth;
+pub mod cache;
+pub mod cancel;
+pub mod config;
+pub mod downloader;
+pub mod engine;
+pub mod error;
+pub mod fingerprint;
+pub mod fs;
+pub mod groups;
+pub mod integrity;
+pub mod manifest;
+pub mod mirror;
+pub mod patch;
+pub mod progress;
+pub mod proxy;
+pub mod signature;
+pub mod stale;
+pub mod updates;
\ No newline at end of file
@@ -0,0 +1,101 @@
+use sha2::Sha256;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+use crate::core::engine::{engine, file_digest, Checksum, DownloadRequest};
+use crate::core::error::DownloadError;
+use crate::core::manifest::ManifestFile;
+use crate::log_info;
+
+const PATCH_DIR: &str = ".limacina/patches";
+// Патчи для файлов крупнее 128 МиБ требуют окна больше значения zstd по умолчанию
+const PATCH_WINDOW_LOG_MAX: u32 = 31;
+
+/// Куда пишется результат патча. Не `.part`: там может лежать недокачанная
+/// полная загрузка этого же файла, которую движок потом продолжит.
+fn patch_output_path(dest: &Path) -> PathBuf {
+    let mut path = dest.as_os_str().to_owned();
+    path.push(".patch.tmp");
+    PathBuf::from(path)
+}
+
+/// Применяет патч `zstd --patch-from=<старый файл>` и пишет результат в `out`.
+/// Старый файл целиком нужен zstd как словарь, поэтому вызывается только из `spawn_blocking`.
+fn apply_zstd_patch(old: &Path, patch: &Path, out: &Path) -> Result<(), DownloadError> {
+    let reference = std::fs::read(old)?;
+    let mut decoder = zstd::stream::read::Decoder::with_ref_prefix(BufReader::new(File::open(patch)?), &reference)?;
+    decoder.window_log_max(PATCH_WINDOW_LOG_MAX)?;
+
+    let mut writer = BufWriter::new(File::create(out)?);
+    std::io::copy(&mut decoder, &mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+}
+
+/// Пытается обновить уже скачанный файл бинарным патчем вместо полной загрузки.
+/// `Ok(false)` — подходящего патча нет; при ошибке вызывающий качает файл целиком.
+pub async fn update_with_patch(
+    core: &Path,
+    file: &ManifestFile,
+    request: &DownloadRequest,
+    cancel: &CancellationToken,
+) -> Result<bool, DownloadError> {
+    if file.patches.is_empty() || !request.dest.exists() {
+        return Ok(false);
+    }
+
+    let current = {
+        let dest = request.dest.clone();
+        tokio::task::spawn_blocking(move || file_digest::<Sha256>(&dest)).await??
+    };
+    let Some(patch) = file
+        .patches
+        .iter()
+        .find(|patch| patch.from_sha256.eq_ignore_ascii_case(&current))
+    else {
+        return Ok(false);
+    };
+
+    log_info!("Обновляем {} патчем", file.path);
+    let patch_path: PathBuf = core
+        .join(PATCH_DIR)
+        .join(format!("{}.patch", current.to_ascii_lowercase()));
+    let mut patch_request = DownloadRequest::new(&patch.url, patch_path.clone()).cancel(cancel);
+    if let Some(sha256) = &patch.sha256 {
+        patch_request = patch_request.checksum(Checksum::Sha256(sha256.clone()));
+    }
+    if let Some(size) = patch.size {
+        patch_request = patch_request.size(size);
+    }
+    engine().download(&patch_request).await?;
+
+    let out = patch_output_path(&request.dest);
+    let applied = {
+        let (patch_path, out, check) = (patch_path.clone(), out.clone(), request.clone());
+        tokio::task::spawn_blocking(move || -> Result<bool, DownloadError> {
+            apply_zstd_patch(&check.dest, &patch_path, &out)?;
+            // Результат проверяется тем же хэшем, что и при полной загрузке
+            Ok(check.verify(&out)?)
+        })
+        .await?
+    };
+    let _ = tokio::fs::remove_file(&patch_path).await;
+
+    match applied {
+        Ok(true) => {}
+        Ok(false) => {
+            let _ = tokio::fs::remove_file(&out).await;
+            return Err(DownloadError::Corrupted(request.dest.to_string_lossy().to_string()));
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&out).await;
+            return Err(e);
+        }
+    }
+
+    tokio::fs::rename(&out, &request.dest).await?;
+    Ok(true)
+}
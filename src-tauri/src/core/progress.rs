@@ -0,0 +1,176 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+const PROGRESS_EVENT: &str = "install-progress";
+const EMIT_INTERVAL: Duration = Duration::from_millis(150);
+// Вес нового замера в сглаженной скорости
+const SPEED_SMOOTHING: f64 = 0.3;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallStage {
+    ClientJar,
+    Libraries,
+    Natives,
+    AssetIndex,
+    Assets,
+    Loader,
+    ModpackSync,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressPayload {
+    pub stage: InstallStage,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub percent: f64,
+    /// Байт в секунду
+    pub speed: u64,
+    /// Оставшееся время в секундах, если его можно оценить
+    pub eta: Option<u64>,
+    pub current_file: Option<String>,
+}
+
+struct SpeedState {
+    last_emit: Instant,
+    last_bytes: u64,
+    speed: f64,
+}
+
+/// Счётчик прогресса одного этапа установки. Движок загрузок сообщает ему байты,
+/// а он с ограничением частоты шлёт во фронтенд событие `install-progress`.
+pub struct ProgressTracker {
+    app: AppHandle,
+    stage: InstallStage,
+    files_total: u64,
+    bytes_total: u64,
+    files_done: AtomicU64,
+    bytes_done: AtomicU64,
+    started: Instant,
+    state: Mutex<SpeedState>,
+}
+
+impl std::fmt::Debug for ProgressTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressTracker")
+            .field("stage", &self.stage)
+            .field("files_done", &self.files_done)
+            .field("bytes_done", &self.bytes_done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProgressTracker {
+    pub fn new(app: &AppHandle, stage: InstallStage, files_total: u64, bytes_total: u64) -> Arc<Self> {
+        let now = Instant::now();
+        let tracker = Arc::new(Self {
+            app: app.clone(),
+            stage,
+            files_total,
+            bytes_total,
+            files_done: AtomicU64::new(0),
+            bytes_done: AtomicU64::new(0),
+            started: now,
+            state: Mutex::new(SpeedState {
+                last_emit: now,
+                last_bytes: 0,
+                speed: 0.0,
+            }),
+        });
+        tracker.emit(None, true);
+        tracker
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+        self.emit(None, false);
+    }
+
+    /// Откатывает байты, которые пришлось выбросить (например, повреждённый `.part`).
+    pub fn sub_bytes(&self, bytes: u64) {
+        let _ = self
+            .bytes_done
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |done| {
+                Some(done.saturating_sub(bytes))
+            });
+    }
+
+    pub fn file_done(&self, name: &str) {
+        let done = self.files_done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.emit(Some(name), done >= self.files_total);
+    }
+
+    pub fn finish(&self) {
+        self.emit(None, true);
+    }
+
+    fn emit(&self, current_file: Option<&str>, force: bool) {
+        let now = Instant::now();
+        let bytes_done = self.bytes_done.load(Ordering::Relaxed);
+
+        let speed = {
+            let mut state = match self.state.lock() {
+                Ok(state) => state,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let elapsed = now.duration_since(state.last_emit);
+            if !force && elapsed < EMIT_INTERVAL {
+                return;
+            }
+            if !elapsed.is_zero() {
+                let sample = bytes_done.saturating_sub(state.last_bytes) as f64 / elapsed.as_secs_f64();
+                state.speed = if state.speed == 0.0 {
+                    sample
+                } else {
+                    state.speed * (1.0 - SPEED_SMOOTHING) + sample * SPEED_SMOOTHING
+                };
+            }
+            state.last_emit = now;
+            state.last_bytes = bytes_done;
+            state.speed
+        };
+
+        let _ = self.app.emit(PROGRESS_EVENT, self.payload(bytes_done, speed, current_file));
+    }
+
+    fn payload(&self, bytes_done: u64, speed: f64, current_file: Option<&str>) -> ProgressPayload {
+        let files_done = self.files_done.load(Ordering::Relaxed);
+        let bytes_total = self.bytes_total;
+
+        let fraction = if bytes_total > 0 {
+            bytes_done as f64 / bytes_total as f64
+        } else if self.files_total > 0 {
+            files_done as f64 / self.files_total as f64
+        } else {
+            1.0
+        };
+        let fraction = fraction.min(1.0);
+
+        // Без известного объёма оцениваем время по доле готовых файлов
+        let eta = if bytes_total > 0 && speed > 0.0 {
+            Some((bytes_total.saturating_sub(bytes_done) as f64 / speed).ceil() as u64)
+        } else if fraction > 0.0 && fraction < 1.0 {
+            let elapsed = self.started.elapsed().as_secs_f64();
+            Some((elapsed / fraction * (1.0 - fraction)).ceil() as u64)
+        } else {
+            None
+        };
+
+        ProgressPayload {
+            stage: self.stage,
+            files_done,
+            files_total: self.files_total,
+            bytes_done,
+            bytes_total,
+            percent: (fraction * 1000.0).round() / 10.0,
+            speed: speed as u64,
+            eta,
+            current_file: current_file.map(str::to_string),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use reqwest::{NoProxy, Proxy, Url};
+
+use crate::core::config::{ProxyConfig, ProxyKind};
+use crate::core::error::DownloadError;
+
+fn proxy_url(proxy: &ProxyConfig) -> Result<Url, DownloadError> {
+    // socks5h: имена хостов разрешает сам прокси
+    let scheme = match proxy.kind {
+        ProxyKind::Http => "http",
+        ProxyKind::Https => "https",
+        ProxyKind::Socks5 => "socks5h",
+    };
+    let address = format!("{}://{}:{}", scheme, proxy.host.trim(), proxy.port);
+    let mut url = Url::parse(&address)
+        .map_err(|e| DownloadError::SystemError(format!("Некорректный адрес прокси {}: {}", address, e)))?;
+
+    if let Some(username) = proxy.username.as_deref().filter(|u| !u.is_empty()) {
+        let credentials = url
+            .set_username(username)
+            .and_then(|_| url.set_password(proxy.password.as_deref()));
+        if credentials.is_err() {
+            return Err(DownloadError::SystemError(
+                "Не удалось задать логин и пароль прокси".to_string(),
+            ));
+        }
+    }
+    Ok(url)
+}
+
+/// Прокси для `reqwest`; `no_proxy` из настроек идёт в обход.
+pub fn reqwest_proxy(proxy: &ProxyConfig) -> Result<Proxy, DownloadError> {
+    let mut result = Proxy::all(proxy_url(proxy)?)?;
+    if !proxy.no_proxy.is_empty() {
+        result = result.no_proxy(NoProxy::from_string(&proxy.no_proxy.join(",")));
+    }
+    Ok(result)
+}
+
+/// Системные свойства Java с теми же настройками прокси. Пусто, если прокси выключен.
+pub fn jvm_args(proxy: &ProxyConfig) -> Vec<String> {
+    if !proxy.enabled {
+        return Vec::new();
+    }
+
+    let host = proxy.host.trim();
+    let username = proxy.username.as_deref().filter(|u| !u.is_empty());
+    let password = proxy.password.as_deref().unwrap_or_default();
+    let mut args = Vec::new();
+
+    match proxy.kind {
+        ProxyKind::Socks5 => {
+            args.push(format!("-DsocksProxyHost={}", host));
+            args.push(format!("-DsocksProxyPort={}", proxy.port));
+            if let Some(username) = username {
+                args.push(format!("-Djava.net.socks.username={}", username));
+                args.push(format!("-Djava.net.socks.password={}", password));
+            }
+        }
+        // У Java нет TLS до самого прокси, поэтому https-прокси передаём как обычный
+        ProxyKind::Http | ProxyKind::Https => {
+            for scheme in ["http", "https"] {
+                args.push(format!("-D{}.proxyHost={}", scheme, host));
+                args.push(format!("-D{}.proxyPort={}", scheme, proxy.port));
+                if let Some(username) = username {
+                    args.push(format!("-D{}.proxyUser={}", scheme, username));
+                    args.push(format!("-D{}.proxyPassword={}", scheme, password));
+                }
+            }
+            if username.is_some() {
+                // Иначе JDK не отправит Basic-авторизацию при CONNECT
+                args.push("-Djdk.http.auth.tunneling.disabledSchemes=".to_string());
+            }
+        }
+    }
+
+    // Java понимает только маски хостов: `.example.com` -> `*.example.com`, подсети пропускаем
+    let non_proxy: Vec<String> = proxy
+        .no_proxy
+        .iter()
+        .map(|host| host.trim())
+        .filter(|host| !host.is_empty() && !host.contains('/'))
+        .map(|host| match host.strip_prefix('.') {
+            Some(domain) => format!("*.{}", domain),
+            None => host.to_string(),
+        })
+        .collect();
+    if !non_proxy.is_empty() {
+        args.push(format!("-Dhttp.nonProxyHosts={}", non_proxy.join("|")));
+    }
+
+    args
+}
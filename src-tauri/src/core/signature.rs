@@ -0,0 +1,176 @@
+use ed25519_dalek::{Signature, VerifyingKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+
+use crate::core::error::DownloadError;
+use crate::log_err;
+
+/// Публичный ключ сервера сборки (hex, 32 байта), зашивается при сборке лаунчера.
+const PINNED_PUBLIC_KEY: Option<&str> = option_env!("LIMACINA_MANIFEST_PUBLIC_KEY");
+
+// Релиз без ключа принимал бы любой манифест, а с битым ключом не принял бы ни
+// одного, поэтому такие сборки не компилируются. Без ключа работают только отладочные.
+#[cfg(not(debug_assertions))]
+const _: () = assert!(
+    is_valid_key(PINNED_PUBLIC_KEY),
+    "LIMACINA_MANIFEST_PUBLIC_KEY must be 64 hex characters for release builds"
+);
+
+/// Ключ — ровно 32 байта в hex, без пробелов.
+#[cfg(any(not(debug_assertions), test))]
+const fn is_valid_key(key: Option<&str>) -> bool {
+    let Some(key) = key else {
+        return false;
+    };
+    let bytes = key.as_bytes();
+    if bytes.len() != PUBLIC_KEY_LENGTH * 2 {
+        return false;
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_hexdigit() {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn pinned_key() -> Result<Option<VerifyingKey>, DownloadError> {
+    PINNED_PUBLIC_KEY.map(parse_key).transpose()
+}
+
+fn parse_key(hex: &str) -> Result<VerifyingKey, DownloadError> {
+    let bytes: [u8; PUBLIC_KEY_LENGTH] = decode_hex(hex)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| DownloadError::Signature("некорректный зашитый публичный ключ".to_string()))?;
+
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| DownloadError::Signature(format!("некорректный зашитый публичный ключ: {}", e)))
+}
+
+/// Подпись принимается как 64 байта или как их hex-запись.
+fn parse_signature(raw: &[u8]) -> Result<Signature, DownloadError> {
+    let bytes = match raw.len() {
+        SIGNATURE_LENGTH => raw.to_vec(),
+        _ => std::str::from_utf8(raw)
+            .ok()
+            .and_then(decode_hex)
+            .ok_or_else(|| DownloadError::Signature("неверный формат подписи".to_string()))?,
+    };
+
+    let bytes: [u8; SIGNATURE_LENGTH] = bytes
+        .try_into()
+        .map_err(|_| DownloadError::Signature("неверная длина подписи".to_string()))?;
+    Ok(Signature::from_bytes(&bytes))
+}
+
+/// Проверяет detached-подпись манифеста зашитым ключом. Отладочные сборки без
+/// ключа пропускают проверку с предупреждением в логе.
+pub fn verify_manifest(manifest: &[u8], signature: Option<&[u8]>) -> Result<(), DownloadError> {
+    let Some(key) = pinned_key()? else {
+        log_err!("Публичный ключ манифеста не задан при сборке, подпись не проверяется");
+        return Ok(());
+    };
+    verify_with_key(&key, manifest, signature)
+}
+
+fn verify_with_key(key: &VerifyingKey, manifest: &[u8], signature: Option<&[u8]>) -> Result<(), DownloadError> {
+    let signature = signature
+        .ok_or_else(|| DownloadError::Signature("сервер не отдал подпись манифеста".to_string()))?;
+
+    key.verify_strict(manifest, &parse_signature(signature)?)
+        .map_err(|_| DownloadError::Signature("подпись не совпадает".to_string()))
+}
+
+pub fn is_enforced() -> bool {
+    PINNED_PUBLIC_KEY.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const MANIFEST: &[u8] = br#"{"version":1,"files":[]}"#;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn verifying_key(seed: u8) -> VerifyingKey {
+        parse_key(&hex(signing_key(seed).verifying_key().as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn accepts_raw_signature() {
+        let signature = signing_key(1).sign(MANIFEST).to_bytes();
+        assert!(verify_with_key(&verifying_key(1), MANIFEST, Some(&signature)).is_ok());
+    }
+
+    #[test]
+    fn accepts_hex_signature() {
+        let signature = hex(&signing_key(1).sign(MANIFEST).to_bytes());
+        let with_newline = format!("{}\n", signature);
+        assert!(verify_with_key(&verifying_key(1), MANIFEST, Some(signature.as_bytes())).is_ok());
+        assert!(verify_with_key(&verifying_key(1), MANIFEST, Some(with_newline.as_bytes())).is_ok());
+    }
+
+    #[test]
+    fn rejects_wrong_key() {
+        let signature = signing_key(2).sign(MANIFEST).to_bytes();
+        assert!(matches!(
+            verify_with_key(&verifying_key(1), MANIFEST, Some(&signature)),
+            Err(DownloadError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_manifest() {
+        let signature = signing_key(1).sign(MANIFEST).to_bytes();
+        let tampered = br#"{"version":1,"files":[{"path":"mods/evil.jar"}]}"#;
+        assert!(matches!(
+            verify_with_key(&verifying_key(1), tampered, Some(&signature)),
+            Err(DownloadError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_signature() {
+        assert!(matches!(
+            verify_with_key(&verifying_key(1), MANIFEST, None),
+            Err(DownloadError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_signature() {
+        assert!(verify_with_key(&verifying_key(1), MANIFEST, Some(b"not a signature")).is_err());
+        assert!(parse_key("abcd").is_err());
+    }
+
+    #[test]
+    fn pinned_key_format_check() {
+        let key = hex(signing_key(1).verifying_key().as_bytes());
+        assert!(is_valid_key(Some(&key)));
+        assert!(is_valid_key(Some(&key.to_uppercase())));
+        assert!(!is_valid_key(None));
+        assert!(!is_valid_key(Some("")));
+        assert!(!is_valid_key(Some("abcd")));
+        assert!(!is_valid_key(Some(&format!("{}\n", key))));
+        assert!(!is_valid_key(Some(&format!("g{}", &key[1..]))));
+    }
+}
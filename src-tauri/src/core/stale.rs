@@ -0,0 +1,273 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::core::config::StaleFilesMode;
+use crate::core::error::DownloadError;
+use crate::core::fingerprint::FingerprintIndex;
+use crate::core::manifest::{normalize_path, safe_join, ModpackManifest};
+use crate::{log_err, log_info};
+
+/// Папки, которыми синхронизация управляет всегда.
+const DEFAULT_MANAGED_DIRS: &[&str] = &["mods", "config"];
+const QUARANTINE_DIR: &str = ".limacina/quarantine";
+
+/// Какие неизвестные манифесту файлы считать лишними.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleScope {
+    /// Только те, что лаунчер сам когда-то установил (есть в индексе отпечатков).
+    /// Настройки, которые моды пишут в `config/`, так не трогаются.
+    Installed,
+    /// Плюс посторонние jar в `mods/` — при явном восстановлении сборки.
+    WithForeignMods,
+}
+
+/// Jar в `mods/`: такие файлы проверка целостности считает посторонними модами.
+pub fn is_mod_jar(relative: &str) -> bool {
+    relative.starts_with("mods/") && relative.to_ascii_lowercase().ends_with(".jar")
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedFile {
+    pub path: String,
+    /// Куда файл перенесён; `None`, если он удалён окончательно.
+    pub quarantined_to: Option<String>,
+}
+
+pub fn build_allow_list<'a>(patterns: impl IntoIterator<Item = &'a String>) -> Result<GlobSet, DownloadError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(&normalize_path(pattern)).map_err(|e| {
+            DownloadError::SystemError(format!("Некорректный шаблон {}: {}", pattern, e))
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| DownloadError::SystemError(e.to_string()))
+}
+
+/// Файлы в управляемых папках, которых нет в манифесте и которые не попадают в allow-list.
+/// Из них лишними считаются только попадающие в `scope`.
+/// Пути возвращаются относительно `base` в виде `dir/file`.
+pub fn find_stale_files(
+    base: &Path,
+    manifest: &ModpackManifest,
+    allow_list: &GlobSet,
+    installed: &FingerprintIndex,
+    scope: StaleScope,
+) -> Result<Vec<String>, DownloadError> {
+    let known: HashSet<String> = manifest
+        .files
+        .iter()
+        .map(|file| file.normalized_path())
+        .collect();
+
+    let managed_dirs = DEFAULT_MANAGED_DIRS
+        .iter()
+        .map(|dir| dir.to_string())
+        .chain(manifest.managed_dirs.iter().map(|dir| normalize_path(dir)))
+        .collect::<HashSet<_>>();
+
+    let mut stale = Vec::new();
+    for dir in managed_dirs {
+        let root = safe_join(base, &dir)?;
+        if !root.is_dir() {
+            continue;
+        }
+
+        for entry in WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(base) else {
+                continue;
+            };
+            let relative = normalize_path(&relative.to_string_lossy());
+
+            // Недокачанный файл из манифеста ещё пригодится для докачки
+            let is_known_part = relative
+                .strip_suffix(".part")
+                .is_some_and(|dest| known.contains(dest));
+
+            if known.contains(&relative) || is_known_part || allow_list.is_match(&relative) {
+                continue;
+            }
+
+            let in_scope = installed.contains(&relative)
+                || (scope == StaleScope::WithForeignMods && is_mod_jar(&relative));
+            if in_scope {
+                stale.push(relative);
+            }
+        }
+    }
+
+    stale.sort();
+    Ok(stale)
+}
+
+fn quarantine_root(base: &Path) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    base.join(QUARANTINE_DIR).join(stamp.to_string())
+}
+
+/// Удаляет или переносит в карантин лишние файлы. Ошибка по одному файлу не
+/// прерывает синхронизацию — такой файл просто не попадает в отчёт.
+pub async fn remove_stale_files(
+    base: &Path,
+    stale: Vec<String>,
+    mode: StaleFilesMode,
+) -> Vec<RemovedFile> {
+    if mode == StaleFilesMode::Keep {
+        return Vec::new();
+    }
+
+    let quarantine = quarantine_root(base);
+    let mut removed = Vec::new();
+
+    for relative in stale {
+        let path = base.join(&relative);
+        let result = match mode {
+            StaleFilesMode::Quarantine => {
+                let target = quarantine.join(&relative);
+                let moved = async {
+                    if let Some(parent) = target.parent() {
+                        tokio::fs::create_dir_all(parent).await?;
+                    }
+                    tokio::fs::rename(&path, &target).await
+                };
+                moved
+                    .await
+                    .map(|_| Some(target.to_string_lossy().to_string()))
+            }
+            _ => tokio::fs::remove_file(&path).await.map(|_| None),
+        };
+
+        match result {
+            Ok(quarantined_to) => {
+                log_info!("Удалён лишний файл: {}", relative);
+                removed.push(RemovedFile {
+                    path: relative,
+                    quarantined_to,
+                });
+            }
+            Err(e) => {
+                log_err!("Не удалось удалить {}: {}", relative, e);
+            }
+        }
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::{Checksum, DownloadRequest};
+
+    const MANIFEST: &str = r#"{"version":1,"files":[
+        {"path":"mods/pack.jar","size":null,"sha256":null,"url":null},
+        {"path":"config/pack.cfg","size":null,"sha256":null,"url":null}
+    ],"keep":["config/*.local"]}"#;
+
+    /// Папка лаунчера: файлы сборки, мод и настройки игрока, старые файлы,
+    /// которые лаунчер ставил раньше, и файлы вне управляемых папок.
+    fn setup(name: &str) -> (PathBuf, FingerprintIndex) {
+        let base = std::env::temp_dir().join(format!("limacina-stale-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        for file in [
+            "mods/pack.jar",
+            "mods/user.jar",
+            "mods/old.jar",
+            "config/pack.cfg",
+            "config/user.cfg",
+            "config/old.cfg",
+            "config/client.local",
+            "saves/world/level.dat",
+            "resourcepacks/old.zip",
+        ] {
+            let path = base.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+
+        let mut installed = FingerprintIndex::load(&base);
+        for file in ["mods/old.jar", "config/old.cfg", "config/client.local", "resourcepacks/old.zip"] {
+            let request = DownloadRequest::new("", base.join(file)).checksum(Checksum::Sha256(String::new()));
+            installed.record(file, &request);
+        }
+        (base, installed)
+    }
+
+    fn find(base: &Path, installed: &FingerprintIndex, scope: StaleScope) -> Vec<String> {
+        let manifest = ModpackManifest::parse(MANIFEST.as_bytes()).unwrap();
+        let allow_list = build_allow_list(&manifest.keep).unwrap();
+        find_stale_files(base, &manifest, &allow_list, installed, scope).unwrap()
+    }
+
+    #[test]
+    fn sync_keeps_files_the_launcher_did_not_install() {
+        let (base, installed) = setup("sync");
+        assert_eq!(find(&base, &installed, StaleScope::Installed), vec!["config/old.cfg", "mods/old.jar"]);
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn repair_also_finds_foreign_mods() {
+        let (base, installed) = setup("repair");
+        assert_eq!(
+            find(&base, &installed, StaleScope::WithForeignMods),
+            vec!["config/old.cfg", "mods/old.jar", "mods/user.jar"]
+        );
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn kept_globs_and_unmanaged_dirs_are_ignored() {
+        let (base, installed) = setup("keep");
+        let stale = find(&base, &installed, StaleScope::WithForeignMods);
+        assert!(!stale.contains(&"config/client.local".to_string()));
+        assert!(!stale.contains(&"resourcepacks/old.zip".to_string()));
+        assert!(!stale.iter().any(|path| path.starts_with("saves/")));
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn unfinished_download_of_a_manifest_file_is_kept() {
+        let (base, installed) = setup("part");
+        std::fs::write(base.join("mods/pack.jar.part"), b"").unwrap();
+        assert!(!find(&base, &installed, StaleScope::WithForeignMods).contains(&"mods/pack.jar.part".to_string()));
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[tokio::test]
+    async fn repair_quarantines_foreign_mod() {
+        let (base, installed) = setup("quarantine");
+        let stale = find(&base, &installed, StaleScope::WithForeignMods);
+        let removed = remove_stale_files(&base, stale, StaleFilesMode::Quarantine).await;
+
+        let user_jar = removed.iter().find(|file| file.path == "mods/user.jar").unwrap();
+        let target = PathBuf::from(user_jar.quarantined_to.as_ref().unwrap());
+        assert!(!base.join("mods/user.jar").exists());
+        assert_eq!(std::fs::read(target).unwrap(), b"mods/user.jar");
+        assert!(base.join("mods/pack.jar").exists());
+        assert!(base.join("config/user.cfg").exists());
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[tokio::test]
+    async fn keep_mode_touches_nothing() {
+        let (base, installed) = setup("keep-mode");
+        let stale = find(&base, &installed, StaleScope::WithForeignMods);
+        assert!(remove_stale_files(&base, stale, StaleFilesMode::Keep).await.is_empty());
+        assert!(base.join("mods/user.jar").exists());
+        assert!(base.join("mods/old.jar").exists());
+        let _ = std::fs::remove_dir_all(base);
+    }
+}
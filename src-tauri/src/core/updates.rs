@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::core::cancel::{is_running, SYNC_OPERATION};
+use crate::core::config::{launcher_config, StaleFilesMode};
+use crate::core::downloader::{download_request, fetch_manifest, file_action, get_base_dir, FileAction};
+use crate::core::error::DownloadError;
+use crate::core::fingerprint::FingerprintIndex;
+use crate::core::groups::{disabled_files, load_choices};
+use crate::core::stale::{build_allow_list, find_stale_files, StaleScope};
+use crate::{log_err, log_info};
+
+const UPDATE_EVENT: &str = "modpack-update";
+// Первая проверка — вскоре после запуска, чтобы не мешать старту окна
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(30);
+
+/// Что изменит следующая синхронизация. Считается по индексу отпечатков,
+/// без хэширования файлов и без скачивания.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpdateSummary {
+    pub update_available: bool,
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    /// Сколько байт скачает синхронизация (по размерам из манифеста).
+    pub total_bytes: u64,
+}
+
+pub async fn check_updates() -> Result<UpdateSummary, DownloadError> {
+    let sync = launcher_config().sync;
+    let manifest = fetch_manifest().await?;
+    let core = PathBuf::from(get_base_dir()?);
+
+    let fingerprints = FingerprintIndex::load(&core);
+    let disabled = disabled_files(&manifest, &load_choices(&core));
+    let mut summary = UpdateSummary::default();
+
+    for file in &manifest.files {
+        let file_path = file.local_path(&core)?;
+        match file_action(file, &file_path, &disabled) {
+            FileAction::Remove => summary.removed.push(file.normalized_path()),
+            FileAction::Leave => {}
+            // Файл, который индекс не может подтвердить, считаем изменённым
+            FileAction::Sync => {
+                let exists = file_path.exists();
+                let request = download_request(&sync, file_path, file);
+                if exists && fingerprints.is_current(&file.normalized_path(), &request) {
+                    continue;
+                }
+                summary.total_bytes += file.size.unwrap_or(0);
+                if exists {
+                    summary.changed.push(file.normalized_path());
+                } else {
+                    summary.added.push(file.normalized_path());
+                }
+            }
+        }
+    }
+
+    if sync.stale_files != StaleFilesMode::Keep {
+        let allow_list = build_allow_list(sync.keep.iter().chain(&manifest.keep))?;
+        summary.removed.extend(find_stale_files(
+            &core,
+            &manifest,
+            &allow_list,
+            &fingerprints,
+            StaleScope::Installed,
+        )?);
+    }
+
+    summary.update_available =
+        !summary.added.is_empty() || !summary.changed.is_empty() || !summary.removed.is_empty();
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn check_modpack_updates() -> Result<UpdateSummary, DownloadError> {
+    check_updates().await
+}
+
+/// Фоновая проверка обновлений сборки. Событие `modpack-update` шлётся,
+/// только когда сводка изменилась с прошлой проверки.
+pub async fn watch_updates(app: AppHandle) {
+    let mut last_summary: Option<UpdateSummary> = None;
+    tokio::time::sleep(FIRST_CHECK_DELAY).await;
+
+    loop {
+        let config = launcher_config();
+        let interval = config.sync.update_check_interval;
+        // Во время синхронизации сводка заведомо неактуальна
+        if interval > 0 && !config.offline && !is_running(SYNC_OPERATION) {
+            match check_updates().await {
+                Ok(summary) if last_summary.as_ref() != Some(&summary) => {
+                    if summary.update_available {
+                        log_info!(
+                            "Доступно обновление сборки: +{} ~{} -{}",
+                            summary.added.len(),
+                            summary.changed.len(),
+                            summary.removed.len()
+                        );
+                        let _ = app.emit(UPDATE_EVENT, summary.clone());
+                    }
+                    last_summary = Some(summary);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log_err!("Не удалось проверить обновления сборки: {}", e);
+                }
+            }
+        }
+
+        tokio::time::sleep(Duration::from_secs(interval.max(60))).await;
+    }
+}
@@ -3,7 +3,13 @@ mod utils;
 mod minecraft;
 mod core;
 
+use core::bandwidth::{downloads_paused, pause_downloads, resume_downloads};
+use core::cancel::cancel_operation;
+use core::config::{get_launcher_config, set_launcher_config};
 use core::downloader::download_all_files;
+use core::groups::{list_mod_groups, set_mod_group};
+use core::integrity::{check_modpack_integrity, repair_modpack};
+use core::updates::{check_modpack_updates, watch_updates};
 use utils::home_dir::get_home_dir;
 use utils::logger_utils;
 use minecraft::jvm::start_jvm;
@@ -17,7 +23,8 @@ pub fn run() {
     tauri::Builder::default()
             .setup(|app| {
                 let handle = app.handle().clone();
-                logger_utils::init_logger(handle);
+                logger_utils::init_logger(handle.clone());
+                tauri::async_runtime::spawn(watch_updates(handle));
 
                 Ok(())
             })
@@ -28,7 +35,18 @@ pub fn run() {
                                                             download_minecraft_version,
                                                             get_fabric,
                                                             get_forge,
-                                                            download_all_files
+                                                            download_all_files,
+                                                            cancel_operation,
+                                                            get_launcher_config,
+                                                            set_launcher_config,
+                                                            list_mod_groups,
+                                                            set_mod_group,
+                                                            check_modpack_updates,
+                                                            check_modpack_integrity,
+                                                            repair_modpack,
+                                                            pause_downloads,
+                                                            resume_downloads,
+                                                            downloads_paused
                                                         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,14 +1,16 @@
-use anyhow::{bail, Context, Result};
-use futures::future;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use tokio::sync::Semaphore;
-use std::sync::Arc;
 use std::env;
 use crate::{log_info, log_err};
-use tauri::{AppHandle, Emitter};
-
-const MAX_CONCURRENT_DOWNLOADS: usize = 20;
+use crate::core::cache::fetch_cached;
+use crate::core::cancel::{begin_operation, INSTALL_OPERATION};
+use crate::core::config::launcher_config;
+use crate::core::engine::{engine, Checksum, DownloadRequest};
+use crate::core::progress::{InstallStage, ProgressTracker};
+use crate::minecraft::install::{offline_install, record_install, FABRIC_INSTALL};
+use tauri::AppHandle;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LoaderVersion {
@@ -148,40 +150,18 @@ pub struct LoggingFile {
     pub url: String,
 }
 
-async fn download_file(client: &reqwest::Client, url: &str, dest: &Path) -> Result<()> { // TODO
-    if dest.exists() {
-              log_info!("Файл уже существует: {:?}", dest);
-              return Ok(());
-     }
-    if let Some(parent) = dest.parent() {
-        tokio::fs::create_dir_all(parent)
-            .await
-            .with_context(|| format!("Не удалось создать директорию для {:?}", dest))?;
-            // fs::create_dir_all(parent)?;
-    }
-
-    let response = client.get(url).send().await?.error_for_status()?;
-    let content = response.bytes().await?;
-
-    tokio::fs::write(dest, &content)
-        .await
-        .with_context(|| format!("Не удалось записать файл в {:?}", dest))?;
-
-    Ok(())
-}
-
 async fn download_fabric_libraries(
-    client: &reqwest::Client,
+    app: &AppHandle,
     json_path: &Path,
     libraries_dir: &Path,
-) -> Result<()> {
+    cancel: &CancellationToken,
+) -> Result<Vec<PathBuf>> {
     let json_data = tokio::fs::read_to_string(json_path).await?;
         let profile: FabricProfile = serde_json::from_str(&json_data)?;
 
         log_info!("Скачивание библиотек Fabric...");
 
-        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS));
-        let mut download_futures = Vec::new();
+        let mut download_requests = Vec::new();
 
         for lib in profile.libraries {
             if lib.url.is_empty() {
@@ -191,7 +171,7 @@ async fn download_fabric_libraries(
 
             let parts: Vec<&str> = lib.name.split(':').collect();
             if parts.len() != 3 {
-                eprintln!("Ошибка: некорректное имя библиотеки: {}", lib.name);
+                log_err!("Ошибка: некорректное имя библиотеки: {}", lib.name);
                 continue;
             }
             let group_id = parts[0];
@@ -212,38 +192,42 @@ async fn download_fabric_libraries(
                 lib.url, group_path, artifact_id, version, file_name
             );
 
-            let client = client.clone();
-            let sem = semaphore.clone();
-            let lib_name = lib.name.clone();
-
-            download_futures.push(async move {
-                let _permit = sem.acquire_owned().await.expect("semaphore closed");
-
-                log_info!("Скачивание {}", lib_name);
-                match download_file(&client, &download_url, &local_path).await {
-                    Ok(_) => Ok(()),
-                    Err(e) => {
-                        eprintln!("Ошибка скачивания {}: {:?}", lib_name, e);
-                        Err(e)
-                    }
-                }
-            });
+            let mut request = DownloadRequest::new(download_url, local_path);
+            if let Some(sha1) = lib.sha1 {
+                request = request.checksum(Checksum::Sha1(sha1));
+            }
+            if let Some(size) = lib.size.and_then(|size| u64::try_from(size).ok()) {
+                request = request.size(size);
+            }
+            download_requests.push(request);
         }
 
-        let results = future::join_all(download_futures).await;
-        let errors: Vec<_> = results.into_iter().filter_map(Result::err).collect();
+        let total_size = download_requests.iter().filter_map(|r| r.size).sum();
+        let progress = ProgressTracker::new(app, InstallStage::Loader, download_requests.len() as u64, total_size);
+        let download_requests = download_requests
+            .into_iter()
+            .map(|r| r.progress(&progress).cancel(cancel))
+            .collect();
 
-        if errors.is_empty() {
+        let results = engine().download_all(download_requests).await;
+        progress.finish();
+        let errors = results.iter().filter(|(_, result)| result.is_err()).count();
+
+        if errors == 0 {
             log_info!("Все библиотеки Fabric успешно скачаны!");
-            Ok(())
+            Ok(results.into_iter().map(|(request, _)| request.dest).collect())
         } else {
-            anyhow::bail!("Не удалось скачать {} библиотек.", errors.len())
+            anyhow::bail!("Не удалось скачать {} библиотек.", errors)
         }
 }
 
 #[tauri::command]
-pub async fn get_fabric(mc_version: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn get_fabric(
+    app: AppHandle,
+    mc_version: String,
+    operation_id: Option<String>,
+) -> Result<String, String> {
+    let operation = begin_operation(operation_id.as_deref().unwrap_or(INSTALL_OPERATION));
     let url = format!("https://meta.fabricmc.net/v2/versions/loader/{}", mc_version);
 
     log_info!("Получение версий Fabric для Minecraft {}...", mc_version);
@@ -253,28 +237,26 @@ pub async fn get_fabric(mc_version: String) -> Result<String, String> {
             .unwrap_or_else(|_| "default_launcher".to_string());
     let base_path = home_dir.join(launcher_name);
 
+    if launcher_config().offline {
+        offline_install(&base_path, FABRIC_INSTALL, &mc_version).map_err(|e| e.to_string())?;
+        log_info!("Автономный режим: Fabric для {} уже установлен", mc_version);
+        return Ok(format!("Fabric для Minecraft {} уже установлен", mc_version));
+    }
 
-   let response_text = client
-       .get(&url)
-       .send()
+   let response_bytes = fetch_cached(&url)
        .await
-       .map_err(|e| format!("Сетевая ошибка при получении версий Fabric: {}", e))?
-       .error_for_status()
-       .map_err(|e| format!("Ошибка от API Fabric: {}", e))?
-       .text()
-       .await
-       .map_err(|e| format!("Ошибка получения текста ответа: {}", e))?;
-
-   //println!("Полученный JSON: {}", response_text);
+       .map_err(|e| format!("Сетевая ошибка при получении версий Fabric: {}", e))?;
 
-   let raw_data: Vec<serde_json::Value> = serde_json::from_str(&response_text)
+   let raw_data: Vec<serde_json::Value> = serde_json::from_slice(&response_bytes)
        .map_err(|e| format!("Ошибка парсинга JSON: {}", e))?;
 
    let mut loaders = vec![];
    for val in raw_data {
        match serde_json::from_value::<LoaderVersion>(val) {
            Ok(v) => loaders.push(v),
-           Err(e) => eprintln!("Пропущена невалидная версия loader: {}", e),
+           Err(e) => {
+               log_err!("Пропущена невалидная версия loader: {}", e);
+           }
        }
    }
 
@@ -306,15 +288,19 @@ pub async fn get_fabric(mc_version: String) -> Result<String, String> {
         .await
         .map_err(|e| format!("Ошибка создания директории {}: {}", version_dir.display(), e))?;
 
+    let loader_progress = ProgressTracker::new(&app, InstallStage::Loader, 2, 0);
+
     let json_dest = version_dir.join(format!("{}.json", version_id));
     log_info!("Скачиваем: {}", json_url);
-    download_file(&client, &json_url, &json_dest)
+    engine()
+        .download(&DownloadRequest::new(&json_url, json_dest.clone()).progress(&loader_progress).cancel(operation.token()))
         .await
         .map_err(|e| format!("Не удалось скачать JSON профиль: {}", e))?;
 
     let jar_dest = version_dir.join(format!("{}.jar", version_id));
     log_info!("Скачиваем: {}", jar_url);
-    download_file(&client, &jar_url, &jar_dest)
+    engine()
+        .download(&DownloadRequest::new(&jar_url, jar_dest.clone()).progress(&loader_progress).cancel(operation.token()))
         .await
         .map_err(|e| format!("Не удалось скачать JAR файл загрузчика: {}", e))?;
 
@@ -322,8 +308,10 @@ pub async fn get_fabric(mc_version: String) -> Result<String, String> {
 
     let libraries_path = base_path.join("libraries");
     log_info!("Fabric: {}", json_dest.display());
-    download_fabric_libraries(&client, &json_dest, &libraries_path)
+    let mut required = download_fabric_libraries(&app, &json_dest, &libraries_path, operation.token())
         .await.map_err(|e| format!("Не удалось скачать библиотеки: {}", e))?;
+    required.extend([json_dest, jar_dest]);
+    record_install(&base_path, FABRIC_INSTALL, &mc_version, &required);
 
     Ok(format!("Fabric {} для Minecraft {} успешно установлен!", loader_ver, mc_version))
 }
\ No newline at end of file
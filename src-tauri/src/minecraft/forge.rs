@@ -1,48 +1,26 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use anyhow::{Context, Result};
 use serde::Deserialize;
 use serde_json::json;
 use tokio::fs;
-use reqwest::Client;
 use tokio::process::Command;
+use tauri::AppHandle;
+
+use crate::core::cache::fetch_cached;
+use crate::core::cancel::{begin_operation, INSTALL_OPERATION};
+use crate::core::config::launcher_config;
+use crate::core::downloader::get_base_dir;
+use crate::core::engine::{engine, DownloadRequest};
+use crate::core::error::DownloadError;
+use crate::core::progress::{InstallStage, ProgressTracker};
+use crate::core::proxy;
+use crate::minecraft::install::{offline_install, record_install, FORGE_INSTALL};
 
 #[derive(Debug, Deserialize)]
 struct ForgePromotions {
     promos: HashMap<String, String>,
 }
 
-fn get_home_dir() -> Option<PathBuf> {
-    #[cfg(target_os = "windows")]
-    {
-        std::env::var("USERPROFILE").ok().map(PathBuf::from)
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        std::env::var("HOME").ok().map(PathBuf::from)
-    }
-}
-
-async fn download_file(client: &Client, url: &str, dest: &PathBuf) -> Result<()> {
-    if let Some(parent) = dest.parent() {
-        fs::create_dir_all(parent).await?;
-    }
-
-    println!("⬇ Скачивание: {}", url);
-
-    let response = client
-        .get(url)
-        .send()
-        .await?
-        .error_for_status()
-        .with_context(|| format!("HTTP ошибка для {}", url))?;
-
-    let content = response.bytes().await?;
-    fs::write(dest, &content).await?;
-
-    Ok(())
-}
-
 async fn cleanup_temp_files(base_dir: &PathBuf, installer_path: &PathBuf) {
     if installer_path.exists() {
         let _ = fs::remove_file(installer_path).await;
@@ -60,28 +38,55 @@ async fn cleanup_temp_files(base_dir: &PathBuf, installer_path: &PathBuf) {
     }
 }
 
+/// Файлы, которые installer положил для запуска: JSON версии и его библиотеки.
+async fn installed_forge_files(base: &Path, version_id: &str) -> Vec<PathBuf> {
+    let json_path = base
+        .join("versions")
+        .join(version_id)
+        .join(format!("{}.json", version_id));
+    let mut files = vec![json_path.clone()];
+
+    let Ok(data) = fs::read(&json_path).await else {
+        return files;
+    };
+    let Ok(version_json) = serde_json::from_slice::<serde_json::Value>(&data) else {
+        return files;
+    };
+
+    let libraries = version_json["libraries"].as_array().into_iter().flatten();
+    for library in libraries {
+        if let Some(path) = library["downloads"]["artifact"]["path"].as_str().filter(|p| !p.is_empty()) {
+            files.push(base.join("libraries").join(path));
+        }
+    }
+    files
+}
+
 #[tauri::command]
-pub async fn get_forge(mc_version: String) -> Result<String, String> {
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (compatible; MinecraftLauncher/1.0)")
-        .build()
-        .map_err(|e| format!("Не удалось создать HTTP клиент: {}", e))?;
+pub async fn get_forge(
+    app: AppHandle,
+    mc_version: String,
+    operation_id: Option<String>,
+) -> Result<String, String> {
+    let operation = begin_operation(operation_id.as_deref().unwrap_or(INSTALL_OPERATION));
+
+    // Та же папка, что у запуска и записей об установках
+    let base = PathBuf::from(get_base_dir().map_err(|e| e.to_string())?);
+
+    if launcher_config().offline {
+        offline_install(&base, FORGE_INSTALL, &mc_version).map_err(|e| e.to_string())?;
+        println!("✓ Автономный режим: Forge для {} уже установлен", mc_version);
+        return Ok(format!("✓ Forge для Minecraft {} уже установлен", mc_version));
+    }
 
     let promotions_url = "https://files.minecraftforge.net/maven/net/minecraftforge/forge/promotions_slim.json";
     println!("🔍 Получение списка версий Forge...");
 
-    let promos_str = client
-        .get(promotions_url)
-        .send()
-        .await
-        .map_err(|e| format!("Не удалось получить список версий Forge: {}", e))?
-        .error_for_status()
-        .map_err(|e| format!("HTTP-ошибка: {}", e))?
-        .text()
+    let promos_bytes = fetch_cached(promotions_url)
         .await
-        .map_err(|e| format!("Не удалось прочитать ответ: {}", e))?;
+        .map_err(|e| format!("Не удалось получить список версий Forge: {}", e))?;
 
-    let promotions: ForgePromotions = serde_json::from_str(&promos_str)
+    let promotions: ForgePromotions = serde_json::from_slice(&promos_bytes)
         .map_err(|e| format!("Не удалось распарсить список версий: {}", e))?;
 
     let forge_version = promotions.promos
@@ -105,20 +110,21 @@ pub async fn get_forge(mc_version: String) -> Result<String, String> {
         forge_full_version
     );
 
-    let home_dir = get_home_dir()
-        .ok_or("Не удалось определить домашнюю директорию")?;
-
-    let launcher_name = std::env::var("LAUNCHER_NAME")
-        .unwrap_or_else(|_| ".minecraft_launcher".to_string());
-
-    let base = home_dir.join(&launcher_name);
-    let installer_path = base.join("forge-installer.jar");
+    // Имя с версией: оставшийся от прерванной установки installer другой версии не подхватится
+    let installer_path = base.join(format!("forge-{}-installer.jar", forge_full_version));
 
     fs::create_dir_all(&base)
         .await
         .map_err(|e| format!("Не удалось создать директорию: {}", e))?;
 
-    download_file(&client, &forge_installer_url, &installer_path)
+    println!("⬇ Скачивание: {}", forge_installer_url);
+    let progress = ProgressTracker::new(&app, InstallStage::Loader, 1, 0);
+    engine()
+        .download(
+            &DownloadRequest::new(&forge_installer_url, installer_path.clone())
+                .progress(&progress)
+                .cancel(operation.token()),
+        )
         .await
         .map_err(|e| format!("Не удалось скачать installer: {}", e))?;
 
@@ -148,14 +154,31 @@ pub async fn get_forge(mc_version: String) -> Result<String, String> {
 
     println!("🔧 Запуск Forge installer...");
 
-    let output = Command::new("java")
+    // kill_on_drop: при отмене процесс installer'а завершается вместе с future
+    // Installer сам скачивает библиотеки, поэтому прокси лаунчера передаём и ему
+    let installer = Command::new("java")
+        .args(proxy::jvm_args(&launcher_config().proxy))
         .arg("-jar")
         .arg(&installer_path)
         .arg("--installClient")
         .arg(base.to_string_lossy().to_string())
-        .output()
-        .await
-        .map_err(|e| format!("Не удалось запустить Forge installer: {}", e))?;
+        .kill_on_drop(true)
+        .output();
+
+    let output = tokio::select! {
+        _ = operation.token().cancelled() => {
+            cleanup_temp_files(&base, &installer_path).await;
+            return Err(DownloadError::Cancelled.to_string());
+        }
+        output = installer => output,
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            cleanup_temp_files(&base, &installer_path).await;
+            return Err(format!("Не удалось запустить Forge installer: {}", e));
+        }
+    };
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
@@ -177,6 +200,10 @@ pub async fn get_forge(mc_version: String) -> Result<String, String> {
 
     println!("✓ Forge установлен успешно!");
 
+    let version_id = format!("{}-forge-{}", mc_version, forge_version);
+    let required = installed_forge_files(&base, &version_id).await;
+    record_install(&base, FORGE_INSTALL, &mc_version, &required);
+
     Ok(format!(
         "✓ Forge {} для Minecraft {} успешно установлен!",
         forge_version, mc_version
@@ -2,18 +2,19 @@ use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::env;
-use std::io::{Cursor, Read, Write};
 use std::path::{Path, PathBuf};
-use tokio::sync::Semaphore;
-use std::sync::Arc;
-use futures::future::join_all;
 use zip::ZipArchive;
 use crate::{log_info, log_err};
+use crate::core::engine::{engine, Checksum, DownloadRequest};
+use crate::core::cache::fetch_cached;
+use crate::core::cancel::{begin_operation, INSTALL_OPERATION};
+use crate::core::config::launcher_config;
+use crate::core::downloader::get_base_dir;
+use crate::core::progress::{InstallStage, ProgressTracker};
+use crate::minecraft::install::{offline_install, record_install, InstallError, InstallReport, VANILLA_INSTALL};
+use tokio_util::sync::CancellationToken;
 use tauri::{AppHandle, Emitter};
 
-const MAX_CONCURRENT_DOWNLOADS: usize = 20;
-
 #[derive(Debug, Deserialize, Serialize)]
 struct VersionsIndexManifest {
     latest: Latest,
@@ -35,6 +36,8 @@ struct VersionInfo {
     time: String,
     #[serde(rename = "releaseTime")]
     release_time: String,
+    /// Есть только в `version_manifest_v2.json`.
+    sha1: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -277,12 +280,9 @@ fn extract_natives_from_jar(jar_path: &Path, natives_dir: &Path, exclude_rules:
 
         let out_path = natives_dir.join(&file_name);
 
-        let mut contents = Vec::new();
-        file.read_to_end(&mut contents)?;
-
         let mut out_file = fs::File::create(&out_path)
             .with_context(|| format!("Не удалось создать файл: {:?}", out_path))?;
-        out_file.write_all(&contents)
+        std::io::copy(&mut file, &mut out_file)
             .with_context(|| format!("Не удалось записать файл: {:?}", out_path))?;
 
         extracted_count += 1;
@@ -293,71 +293,117 @@ fn extract_natives_from_jar(jar_path: &Path, natives_dir: &Path, exclude_rules:
 }
 
 async fn get_version_manifest() -> Result<VersionsIndexManifest> {
-    let url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
-    let resp = reqwest::get(url).await?;
-    let manifest = resp.json::<VersionsIndexManifest>().await?;
+    let url = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+    let manifest = serde_json::from_slice(&fetch_cached(url).await?)?;
     Ok(manifest)
 }
 
-async fn download_file(url: &str, path: &Path) -> Result<()> {
-    if path.exists() {
-        return Ok(());
-    }
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-
-    let response = reqwest::get(url).await?;
+fn sha1_request(url: &str, path: PathBuf, sha1: &str, size: u64, cancel: &CancellationToken) -> DownloadRequest {
+    DownloadRequest::new(url, path)
+        .checksum(Checksum::Sha1(sha1.to_string()))
+        .size(size)
+        .cancel(cancel)
+}
 
-    if !response.status().is_success() {
-        return Err(anyhow!("Неправильный ответ: {}", response.status()));
+fn ensure_not_cancelled(cancel: &CancellationToken) -> Result<(), InstallError> {
+    if cancel.is_cancelled() {
+        log_info!("Установка отменена");
+        return Err(InstallError::Cancelled);
     }
-
-    let content = response.bytes().await?;
-    let mut file = fs::File::create(path)?;
-    let mut content_cursor = Cursor::new(content);
-
-    std::io::copy(&mut content_cursor, &mut file)?;
-
     Ok(())
 }
 
+/// Прерывает установку, если какой-то обязательный файл не скачался.
+fn ensure_complete(report: &InstallReport) -> Result<(), InstallError> {
+    if report.failed.is_empty() {
+        return Ok(());
+    }
+    for failed in &report.failed {
+        log_err!("Не установлен {}: {}", failed.path, failed.reason);
+    }
+    Err(InstallError::Incomplete {
+        report: report.clone().finish(),
+    })
+}
+
 #[tauri::command]
-pub async fn download_minecraft_version(app: AppHandle, version: &str) -> Result<String, String> {
+pub async fn download_minecraft_version(
+    app: AppHandle,
+    version: &str,
+    operation_id: Option<String>,
+) -> Result<InstallReport, InstallError> {
+    let operation = begin_operation(operation_id.as_deref().unwrap_or(INSTALL_OPERATION));
+    let mut report = InstallReport::new(version);
+
+    let base_path = PathBuf::from(get_base_dir()?);
+
+    if launcher_config().offline {
+        let record = offline_install(&base_path, VANILLA_INSTALL, version)?;
+        log_info!("Автономный режим: {} уже установлена, загрузка не нужна", version);
+        report.skipped = record.files.len() as u64;
+        return Ok(report.finish());
+    }
+
     let manifest = get_version_manifest().await.map_err(|e| format!("Ошибка получения манифеста: {}", e))?;
     log_info!("Downloading version {}", version);
-    let version_url = manifest.versions.iter()
+    let version_info = manifest.versions.iter()
         .find(|v| v.id == version)
-        .map(|v| v.url.clone());
-
-    match version_url {
-        Some(url) => {
-            log_info!("Downloading version {}", url);
-            let result = download_files(&url).await;
-            Ok("Download complete".to_string())
-        }
-        None => Err("Err".to_string()),
-    }
+        .ok_or_else(|| InstallError::VersionNotFound { version: version.to_string() })?;
+
+    log_info!("Downloading version {}", version_info.url);
+    let required = download_files(&app, &base_path, version_info, operation.token(), &mut report).await?;
+    ensure_not_cancelled(operation.token())?;
+    ensure_complete(&report)?;
+    record_install(&base_path, VANILLA_INSTALL, version, &required);
+
+    let report = report.finish();
+    log_info!(
+        "Установка {} завершена: скачано {}, пропущено {}, {} байт за {} мс",
+        report.version,
+        report.downloaded,
+        report.skipped,
+        report.bytes_transferred,
+        report.duration_ms
+    );
+    Ok(report)
 }
 
-async fn download_files(manifest_url: &str) -> Result<String, String> {
+/// Скачивает версию и возвращает файлы, без которых она не запустится
+/// (ресурсы в их число не входят).
+async fn download_files(
+    app: &AppHandle,
+    base_path: &Path,
+    version_info: &VersionInfo,
+    cancel: &CancellationToken,
+    report: &mut InstallReport,
+) -> Result<Vec<PathBuf>, InstallError> {
+
+    // JSON версии сохраняется байт в байт: его читают vanilla_start и inheritsFrom у Forge/Fabric
     log_info!("Получение манифеста версии...");
-    let resp = reqwest::get(manifest_url).await.map_err(|e| format!("Ошибка HTTP запроса: {}", e))?;
-    let manifest: VersionDetailsManifest = resp.json().await
+    let version_json_path = base_path
+        .join("versions")
+        .join(&version_info.id)
+        .join(format!("{}.json", version_info.id));
+    let mut version_json_request = DownloadRequest::new(&version_info.url, version_json_path.clone()).cancel(cancel);
+    if let Some(sha1) = &version_info.sha1 {
+        version_json_request = version_json_request.checksum(Checksum::Sha1(sha1.clone()));
+    }
+    let result = engine().download(&version_json_request).await;
+    report.record(&version_json_request, &result);
+    ensure_not_cancelled(cancel)?;
+    ensure_complete(report)?;
+
+    let version_json = fs::read(&version_json_path)
+        .map_err(|e| format!("Не удалось прочитать {:?}: {}", version_json_path, e))?;
+    let manifest: VersionDetailsManifest = serde_json::from_slice(&version_json)
         .map_err(|e| format!("Ошибка при разборе манифеста: {}", e))?;
 
-    let home_dir: PathBuf = env::home_dir().ok_or("Не удалось получить домашнюю директорию".to_string())?;
-    let launcher_name: String = env::var("LAUNCHER_NAME")
-        .unwrap_or_else(|_| "default_launcher".to_string());
-    let base_path: PathBuf = home_dir.join(&launcher_name);
-
     let client_jar_path = base_path.join("versions").join(&manifest.id).join(format!("{}.jar", manifest.id));
     log_info!("Скачиваем основной JAR-файл: {}", manifest.downloads.client.url);
 
-    if let Err(e) = download_file(&manifest.downloads.client.url, &client_jar_path).await {
-        eprintln!("Ошибка при скачивании JAR-файла клиента: {:?}", e);
-        return Err(e.to_string());
-    }
+    let client = &manifest.downloads.client;
+    let client_progress = ProgressTracker::new(app, InstallStage::ClientJar, 1, client.size);
+    let client_request = sha1_request(&client.url, client_jar_path, &client.sha1, client.size, cancel).progress(&client_progress);
 
     let natives_dir = base_path.join("natives").join(&manifest.id);
     fs::create_dir_all(&natives_dir).map_err(|e| format!("Ошибка создания папки natives: {}", e))?;
@@ -365,7 +411,9 @@ async fn download_files(manifest_url: &str) -> Result<String, String> {
     log_info!("\nСкачиваем библиотеки...");
     log_info!("Текущая ОС: {}", get_current_os());
 
-    let mut natives_to_extract: Vec<(PathBuf, Option<Vec<String>>)> = Vec::new();
+    // Сначала собираем список файлов, чтобы знать общий объём для прогресса.
+    // Для natives запоминаем правила исключения при распаковке.
+    let mut library_downloads: Vec<(DownloadRequest, bool, Option<Vec<String>>)> = Vec::new();
 
     for lib in &manifest.libraries {
         if !check_rules(&lib.rules) {
@@ -377,19 +425,18 @@ async fn download_files(manifest_url: &str) -> Result<String, String> {
             continue;
         }
 
+        let exclude = lib.extract.as_ref().and_then(|e| e.exclude.clone());
+
         // (1.19+) natives
         if is_native_jar(&lib.name) {
             if let Some(artifact) = &lib.downloads.artifact {
                 if !artifact.url.is_empty() {
                     let lib_path = base_path.join("libraries").join(&artifact.path);
-                    log_info!("Скачиваем нативную библиотеку: {}", lib.name);
-
-                    if let Err(e) = download_file(&artifact.url, &lib_path).await {
-                        eprintln!("  Ошибка при скачивании: {:?}", e);
-                    } else {
-                        let exclude = lib.extract.as_ref().and_then(|e| e.exclude.clone());
-                        natives_to_extract.push((lib_path, exclude));
-                    }
+                    library_downloads.push((
+                        sha1_request(&artifact.url, lib_path, &artifact.sha1, artifact.size, cancel),
+                        true,
+                        exclude,
+                    ));
                 }
             }
             continue;
@@ -400,11 +447,11 @@ async fn download_files(manifest_url: &str) -> Result<String, String> {
         if let Some(artifact) = &lib.downloads.artifact {
             if !artifact.url.is_empty() {
                 let lib_path = base_path.join("libraries").join(&artifact.path);
-                log_info!("Скачиваем библиотеку: {}", lib.name);
-
-                if let Err(e) = download_file(&artifact.url, &lib_path).await {
-                    eprintln!("  Ошибка при скачивании: {:?}", e);
-                }
+                library_downloads.push((
+                    sha1_request(&artifact.url, lib_path, &artifact.sha1, artifact.size, cancel),
+                    false,
+                    None,
+                ));
             }
         }
 
@@ -419,31 +466,62 @@ async fn download_files(manifest_url: &str) -> Result<String, String> {
                 if let Some(classifiers) = &lib.downloads.classifiers {
                     if let Some(native_artifact) = classifiers.get(&classifier) {
                         let native_jar_path = base_path.join("libraries").join(&native_artifact.path);
-                        log_info!("Скачиваем natives через classifier: {} ({})", lib.name, classifier);
-
-                        if let Err(e) = download_file(&native_artifact.url, &native_jar_path).await {
-                            log_info!("  Ошибка при скачивании: {:?}", e);
-                        } else {
-                            let exclude = lib.extract.as_ref().and_then(|e| e.exclude.clone());
-                            natives_to_extract.push((native_jar_path, exclude));
-                        }
+                        log_info!("Natives через classifier: {} ({})", lib.name, classifier);
+                        library_downloads.push((
+                            sha1_request(&native_artifact.url, native_jar_path, &native_artifact.sha1, native_artifact.size, cancel),
+                            true,
+                            exclude,
+                        ));
                     }
                 }
             }
         }
     }
 
+    let libraries_size = library_downloads.iter().filter_map(|(r, _, _)| r.size).sum();
+    let libraries_progress = ProgressTracker::new(app, InstallStage::Libraries, library_downloads.len() as u64, libraries_size);
+
+    // Клиент и библиотеки качаются параллельно под общим лимитом движка, у каждого
+    // свой этап прогресса; natives распаковываются, только когда все jar уже на месте
+    let mut requests = vec![client_request];
+    let mut natives_info = vec![None];
+    for (request, is_native, exclude) in library_downloads {
+        requests.push(request.progress(&libraries_progress));
+        natives_info.push(is_native.then_some(exclude));
+    }
+    log_info!("Библиотек для загрузки: {}", requests.len() - 1);
+
+    let results = engine().download_all(requests).await;
+    client_progress.finish();
+    libraries_progress.finish();
+    ensure_not_cancelled(cancel)?;
+
+    let mut required = vec![version_json_path.clone()];
+    let mut natives_to_extract: Vec<(PathBuf, Option<Vec<String>>)> = Vec::new();
+    for ((request, result), native) in results.into_iter().zip(natives_info) {
+        report.record(&request, &result);
+        required.push(request.dest.clone());
+        if let (Ok(_), Some(exclude)) = (result, native) {
+            natives_to_extract.push((request.dest, exclude));
+        }
+    }
+
     log_info!("\n=== Извлечение natives ===");
     log_info!("Всего JAR файлов для извлечения: {}", natives_to_extract.len());
 
+    let natives_progress = ProgressTracker::new(app, InstallStage::Natives, natives_to_extract.len() as u64, 0);
     let mut total_extracted = 0u32;
     for (jar_path, exclude_rules) in &natives_to_extract {
         match extract_natives_from_jar(jar_path, &natives_dir, exclude_rules) {
             Ok(count) => {
                 total_extracted += count;
             }
-            Err(e) => eprintln!("  Ошибка извлечения {:?}: {:?}", jar_path, e),
+            Err(e) => {
+                log_err!("  Ошибка извлечения {:?}: {:?}", jar_path, e);
+                report.fail(jar_path, "", format!("Ошибка извлечения natives: {}", e));
+            }
         }
+        natives_progress.file_done(&jar_path.file_name().unwrap_or_default().to_string_lossy());
     }
     log_info!("Всего извлечено нативных файлов: {}", total_extracted);
 
@@ -460,15 +538,25 @@ async fn download_files(manifest_url: &str) -> Result<String, String> {
                 }
             }
         }
-        Err(e) => eprintln!("Ошибка чтения папки natives: {:?}", e),
+        Err(e) => {
+            log_err!("Ошибка чтения папки natives: {:?}", e);
+        }
     }
 
+    ensure_not_cancelled(cancel)?;
     log_info!("\nСкачиваем индекс ресурсов...");
     let asset_index_path = base_path.join("assets").join("indexes").join(format!("{}.json", manifest.asset_index.id));
 
-    download_file(&manifest.asset_index.url, &asset_index_path)
-        .await
-        .map_err(|e| format!("Ошибка при скачивании индекса ресурсов: {:?}", e))?;
+    let asset_index_meta = &manifest.asset_index;
+    let asset_index_progress = ProgressTracker::new(app, InstallStage::AssetIndex, 1, asset_index_meta.size);
+    let asset_index_request = sha1_request(&asset_index_meta.url, asset_index_path.clone(), &asset_index_meta.sha1, asset_index_meta.size, cancel)
+        .progress(&asset_index_progress);
+    let result = engine().download(&asset_index_request).await;
+    report.record(&asset_index_request, &result);
+    ensure_not_cancelled(cancel)?;
+    // Без индекса ресурсы не скачать
+    ensure_complete(report)?;
+    required.push(asset_index_path.clone());
 
     let asset_index_file = fs::read_to_string(&asset_index_path)
         .map_err(|e| format!("Ошибка при чтении индекса ресурсов: {}", e))?;
@@ -477,50 +565,40 @@ async fn download_files(manifest_url: &str) -> Result<String, String> {
         .map_err(|e| format!("Ошибка при разборе индекса ресурсов: {}", e))?;
 
     log_info!("Скачиваем ресурсы...");
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS));
-    let base_path = Arc::new(base_path);
-    let mut download_tasks = Vec::new();
-
-    for (asset_path_key, asset) in asset_index.objects {
-        let hash_prefix = asset.hash[..2].to_string();
-        let asset_hash = asset.hash.clone();
-        let asset_url = format!("https://resources.download.minecraft.net/{}/{}", hash_prefix, asset_hash);
-        let asset_file_path = base_path.join("assets").join("objects").join(&hash_prefix).join(&asset_hash);
-
-        let semaphore = Arc::clone(&semaphore);
-        let asset_path_key = asset_path_key.clone();
-
-        let task = tokio::spawn(async move {
-            let _permit = semaphore.acquire().await.unwrap();
-
-            match download_file(&asset_url, &asset_file_path).await {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    eprintln!("Ошибка при скачивании ресурса {}: {:?}", asset_path_key, e);
-                    Err(e)
-                }
-            }
-        });
-
-        download_tasks.push(task);
+    let assets_progress = ProgressTracker::new(
+        app,
+        InstallStage::Assets,
+        asset_index.objects.len() as u64,
+        manifest.asset_index.total_size,
+    );
+    let mut download_requests = Vec::new();
+
+    for asset in asset_index.objects.values() {
+        let hash_prefix = &asset.hash[..2];
+        let asset_url = format!("https://resources.download.minecraft.net/{}/{}", hash_prefix, asset.hash);
+        let asset_file_path = base_path.join("assets").join("objects").join(hash_prefix).join(&asset.hash);
+
+        download_requests.push(sha1_request(&asset_url, asset_file_path, &asset.hash, asset.size, cancel).progress(&assets_progress));
     }
 
-    log_info!("Всего ресурсов для загрузки: {}", download_tasks.len());
+    log_info!("Всего ресурсов для загрузки: {}", download_requests.len());
 
-    let results = join_all(download_tasks).await;
+    let results = engine().download_all(download_requests).await;
+    assets_progress.finish();
+    ensure_not_cancelled(cancel)?;
 
-    let mut successful = 0;
-    let mut failed = 0;
-
-    for result in results {
-        match result {
-            Ok(Ok(())) => successful += 1,
-            _ => failed += 1,
-        }
+    let failed_before = report.failed.len();
+    for (request, result) in &results {
+        report.record(request, result);
     }
-    log_info!("Загрузка ресурсов завершена. Успешно: {}, Ошибок: {}", successful, failed);
+    log_info!(
+        "Загрузка ресурсов завершена. Успешно: {}, Ошибок: {}",
+        results.len() - (report.failed.len() - failed_before),
+        report.failed.len() - failed_before
+    );
 
+    ensure_complete(report)?;
     log_info!("\nВсе файлы Minecraft успешно скачаны!");
-    Ok("Ok".to_string())
+    Ok(required)
 }
 
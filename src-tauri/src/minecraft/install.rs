@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::core::engine::{DownloadOutcome, DownloadRequest, DownloadResult};
+use crate::core::error::DownloadError;
+use crate::core::fs::write_atomic;
+use crate::log_err;
+
+const INSTALLS_FILE: &str = ".limacina/installs.json";
+
+/// Виды установок; совпадают с `type_minecraft` у `start_jvm`.
+pub const VANILLA_INSTALL: &str = "vanilla";
+pub const FABRIC_INSTALL: &str = "fabric";
+pub const FORGE_INSTALL: &str = "forge";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedFile {
+    pub path: String,
+    pub url: String,
+    pub reason: String,
+}
+
+/// Итог установки версии для фронтенда.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallReport {
+    pub version: String,
+    pub downloaded: u64,
+    /// Файлы, которые уже были на диске и прошли проверку.
+    pub skipped: u64,
+    pub failed: Vec<FailedFile>,
+    pub bytes_transferred: u64,
+    pub duration_ms: u64,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl InstallReport {
+    pub fn new(version: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            downloaded: 0,
+            skipped: 0,
+            failed: Vec::new(),
+            bytes_transferred: 0,
+            duration_ms: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Учитывает результат загрузки. Отмена не считается ошибкой файла —
+    /// её обрабатывает сама установка.
+    pub fn record(&mut self, request: &DownloadRequest, result: &DownloadResult) {
+        match result {
+            Ok(DownloadOutcome::Skipped) => self.skipped += 1,
+            Ok(DownloadOutcome::Downloaded { bytes }) => {
+                self.downloaded += 1;
+                self.bytes_transferred += bytes;
+            }
+            Err(DownloadError::Cancelled) => {}
+            Err(e) => self.fail(&request.dest, &request.url, e),
+        }
+    }
+
+    pub fn fail(&mut self, path: &Path, url: &str, reason: impl ToString) {
+        self.failed.push(FailedFile {
+            path: path.to_string_lossy().to_string(),
+            url: url.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn finish(mut self) -> Self {
+        self.duration_ms = self.started.elapsed().as_millis() as u64;
+        self
+    }
+}
+
+/// Ошибка установки версии. Сериализуется с полем `kind`, чтобы фронтенд мог
+/// предложить нужное действие (повторить, проверить сеть и т.д.).
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InstallError {
+    #[error("Операция отменена")]
+    Cancelled,
+
+    #[error("Версия {version} не найдена")]
+    VersionNotFound { version: String },
+
+    #[error("Не удалось скачать обязательные файлы: {}", report.failed.len())]
+    Incomplete { report: InstallReport },
+
+    /// Автономный режим, а версии нет на диске.
+    #[error("{message}")]
+    Offline { message: String, missing: Vec<String> },
+
+    #[error("{message}")]
+    Failed { message: String },
+}
+
+impl From<String> for InstallError {
+    fn from(message: String) -> Self {
+        InstallError::Failed { message }
+    }
+}
+
+impl From<DownloadError> for InstallError {
+    fn from(error: DownloadError) -> Self {
+        match error {
+            DownloadError::Cancelled => InstallError::Cancelled,
+            e => InstallError::Failed { message: e.to_string() },
+        }
+    }
+}
+
+/// Запись об установленной версии или загрузчике. По ним автономный режим
+/// отличает «уже установлено» от «нужна сеть».
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallRecord {
+    /// Файлы, без которых игра не запустится, относительно папки лаунчера.
+    pub files: Vec<String>,
+    /// Секунды с эпохи Unix.
+    pub installed_at: u64,
+}
+
+/// Ключ записи: вид установки и версия Minecraft.
+fn record_key(kind: &str, version: &str) -> String {
+    format!("{}:{}", kind, version)
+}
+
+fn load_records(base: &Path) -> BTreeMap<String, InstallRecord> {
+    std::fs::read(base.join(INSTALLS_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Запоминает успешную установку. Ошибка записи только логируется: игра
+/// установлена, просто без сети её потом не запустить.
+pub fn record_install(base: &Path, kind: &str, version: &str, files: &[PathBuf]) {
+    let mut records = load_records(base);
+    let files = files
+        .iter()
+        .filter_map(|file| file.strip_prefix(base).ok())
+        .map(|file| file.to_string_lossy().replace('\\', "/"))
+        .collect();
+    let installed_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    records.insert(record_key(kind, version), InstallRecord { files, installed_at });
+
+    let path = base.join(INSTALLS_FILE);
+    let result = serde_json::to_vec_pretty(&records)
+        .map_err(std::io::Error::from)
+        .and_then(|data| write_atomic(&path, &data));
+
+    if let Err(e) = result {
+        log_err!("Не удалось сохранить запись об установке {}: {}", path.display(), e);
+    }
+}
+
+pub fn find_install(base: &Path, kind: &str, version: &str) -> Option<InstallRecord> {
+    load_records(base).remove(&record_key(kind, version))
+}
+
+/// Файлы записи, которых нет на диске.
+pub fn missing_files(base: &Path, record: &InstallRecord) -> Vec<String> {
+    record
+        .files
+        .iter()
+        .filter(|file| !base.join(file).is_file())
+        .cloned()
+        .collect()
+}
+
+/// Установка в автономном режиме: без сети можно только убедиться, что
+/// версия уже целиком лежит на диске.
+pub fn offline_install(base: &Path, kind: &str, version: &str) -> Result<InstallRecord, InstallError> {
+    let Some(record) = find_install(base, kind, version) else {
+        return Err(InstallError::Offline {
+            message: format!("{} {} не установлен, а автономный режим не позволяет его скачать", kind, version),
+            missing: Vec::new(),
+        });
+    };
+
+    let missing = missing_files(base, &record);
+    if missing.is_empty() {
+        return Ok(record);
+    }
+    Err(InstallError::Offline {
+        message: format!(
+            "{} {}: на диске не хватает файлов ({}), а сеть отключена: {}",
+            kind,
+            version,
+            missing.len(),
+            missing.join(", ")
+        ),
+        missing,
+    })
+}
@@ -16,6 +16,10 @@ use uuid::{Builder, Variant, Version};
 use tauri::{AppHandle, Emitter};
 use std::io::{BufRead, BufReader};
 use crate::{log_info, log_err};
+use crate::core::config::launcher_config;
+use crate::core::integrity::{check_integrity, IntegrityIssue};
+use crate::core::proxy;
+use crate::minecraft::install::{find_install, missing_files, FABRIC_INSTALL, FORGE_INSTALL, VANILLA_INSTALL};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -556,6 +560,12 @@ fn spawn_game_process(
     log_info!("\n▶ Запуск Minecraft...\n");
     let mut command = Command::new(java_path);
 
+    // Свойства прокси должны идти до главного класса, поэтому ставим их первыми
+    let proxy = launcher_config().proxy;
+    if proxy.apply_to_game {
+        command.args(proxy::jvm_args(&proxy));
+    }
+
     command.args(args)
         .current_dir(game_dir)
         .stdout(Stdio::piped())
@@ -1177,6 +1187,100 @@ fn generate_offline_uuid(nickname: &str) -> String {
     builder.into_uuid().to_string()
 }
 
+/// Ошибка запуска. Несовпадение с манифестом сервера приходит со списком файлов,
+/// чтобы фронтенд мог предложить восстановление (`repair_modpack`).
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LaunchError {
+    Integrity {
+        message: String,
+        issues: Vec<IntegrityIssue>,
+    },
+    /// Версия не установлена, а в автономном режиме её не скачать.
+    NotInstalled {
+        message: String,
+    },
+    /// Файлы из записи об установке пропали с диска.
+    MissingFiles {
+        message: String,
+        files: Vec<String>,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl From<String> for LaunchError {
+    fn from(message: String) -> Self {
+        LaunchError::Failed { message }
+    }
+}
+
+async fn ensure_integrity() -> Result<(), LaunchError> {
+    if !launcher_config().sync.verify_before_launch {
+        return Ok(());
+    }
+
+    // Недоступность сервера не должна мешать играть — проверка только предупреждает
+    let issues = match check_integrity().await {
+        Ok(issues) => issues,
+        Err(e) => {
+            log_err!("Не удалось проверить файлы сборки перед запуском: {}", e);
+            return Ok(());
+        }
+    };
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+    for issue in &issues {
+        log_err!("Файл сборки не совпадает с сервером ({:?}): {}", issue.kind, issue.path);
+    }
+    Err(LaunchError::Integrity {
+        message: format!("Файлы сборки не совпадают с сервером: {}", issues.len()),
+        issues,
+    })
+}
+
+/// Сверяет с записями об установке, что всё нужное для запуска лежит на диске.
+/// Версии, поставленные до появления записей, онлайн запускаются как раньше.
+fn ensure_installed(type_minecraft: &str, version: &str, offline: bool) -> Result<(), LaunchError> {
+    let base_dir = get_launcher_dir().map_err(|e| e.to_string())?;
+    let kinds: &[&str] = match type_minecraft {
+        "vanilla" => &[VANILLA_INSTALL],
+        "fabric" => &[VANILLA_INSTALL, FABRIC_INSTALL],
+        "forge" => &[VANILLA_INSTALL, FORGE_INSTALL],
+        _ => return Ok(()),
+    };
+
+    let mut missing = Vec::new();
+    for kind in kinds {
+        match find_install(&base_dir, kind, version) {
+            Some(record) => missing.extend(missing_files(&base_dir, &record)),
+            None if offline => {
+                return Err(LaunchError::NotInstalled {
+                    message: format!(
+                        "{} {} не установлен, а в автономном режиме запуск возможен только из установленных версий",
+                        kind, version
+                    ),
+                });
+            }
+            None => {}
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    for file in &missing {
+        log_err!("Не найден файл, нужный для запуска: {}", file);
+    }
+    Err(LaunchError::MissingFiles {
+        message: format!("Не хватает файлов для запуска ({}): {}", missing.len(), missing.join(", ")),
+        files: missing,
+    })
+}
+
 #[tauri::command]
 pub async fn start_jvm(
     app: AppHandle,
@@ -1184,10 +1288,18 @@ pub async fn start_jvm(
     access_token: String,
     type_minecraft: String,
     mc_version: Option<String>,
-) -> Result<String, String> {
+) -> Result<String, LaunchError> {
     let version = mc_version.unwrap_or_else(|| "1.20.1".to_string());
     let uuid = generate_offline_uuid(&username);
 
+    let offline = launcher_config().offline;
+    if offline {
+        log_info!("Автономный режим: запуск без обращения к сети, проверка сборки пропущена");
+    } else {
+        ensure_integrity().await?;
+    }
+    ensure_installed(&type_minecraft, &version, offline)?;
+
     match type_minecraft.as_str() {
         "forge" => {
             forge_start(app, username, uuid, access_token, version)
@@ -1207,7 +1319,7 @@ pub async fn start_jvm(
                 .map_err(|e| e.to_string())?;
             Ok("Vanilla запущен успешно".to_string())
         }
-        _ => Err(format!("Неизвестный тип: {}", type_minecraft)),
+        _ => Err(format!("Неизвестный тип: {}", type_minecraft).into()),
     }
 }
 
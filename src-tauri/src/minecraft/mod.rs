@@ -1,4 +1,5 @@
 pub mod jvm;
 pub mod get_manifest;
 pub mod fabric;
-pub mod forge;
\ No newline at end of file
+pub mod forge;
+pub mod install;
\ No newline at end of file